@@ -0,0 +1,148 @@
+//! Compare the performances of the `LzwDecoder` with the ones of its previous
+//! implementation, on frames of a large animation.
+//!
+//! Run with `cargo bench --bench lzw`.
+
+mod legacy;
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use gif_renderer::LzwDecoder;
+use std::collections::HashMap;
+
+/// Dimensions of the decoded frames, in pixels.
+const WIDTH: usize = 512;
+const HEIGHT: usize = 512;
+
+/// LZW minimum code size used by the frames, for 256 colors.
+const MIN_CODE_SIZE: u8 = 8;
+
+/// Maximum size of a GIF sub-block, in which the compressed data is given to
+/// the decoders.
+const SUB_BLOCK_SIZE: usize = 255;
+
+fn bench_lzw(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lzw");
+    group.throughput(Throughput::Elements((WIDTH * HEIGHT) as u64));
+    for (name, indices) in [("gradient", gradient()), ("noise", noise())] {
+        let data = compress(&indices, MIN_CODE_SIZE);
+
+        group.bench_with_input(BenchmarkId::new("legacy", name), &data, |b, data| {
+            b.iter(|| {
+                let mut decoder = legacy::LzwDecoder::new(MIN_CODE_SIZE).unwrap();
+                let mut nb_decoded = 0;
+                for sub_block in data.chunks(SUB_BLOCK_SIZE) {
+                    nb_decoded += decoder.decode_next(black_box(sub_block)).unwrap().len();
+                }
+                assert_eq!(nb_decoded, indices.len());
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("decode_next", name), &data, |b, data| {
+            b.iter(|| {
+                let mut decoder = LzwDecoder::new(MIN_CODE_SIZE).unwrap();
+                let mut nb_decoded = 0;
+                for sub_block in data.chunks(SUB_BLOCK_SIZE) {
+                    nb_decoded += decoder.decode_next(black_box(sub_block)).unwrap().len();
+                }
+                assert_eq!(nb_decoded, indices.len());
+            })
+        });
+
+        let mut out = vec![0; indices.len()];
+        group.bench_with_input(BenchmarkId::new("decode_into", name), &data, |b, data| {
+            b.iter(|| {
+                let mut decoder = LzwDecoder::new(MIN_CODE_SIZE).unwrap();
+                let mut nb_decoded = 0;
+                for sub_block in data.chunks(SUB_BLOCK_SIZE) {
+                    let progress =
+                        decoder.decode_into(black_box(sub_block), &mut out[nb_decoded..]);
+                    progress.result.unwrap();
+                    nb_decoded += progress.written;
+                }
+                assert_eq!(nb_decoded, indices.len());
+            })
+        });
+        assert_eq!(out, indices);
+    }
+    group.finish();
+}
+
+/// A frame made of diagonal bands of colors, compressing well.
+fn gradient() -> Vec<u8> {
+    (0..HEIGHT)
+        .flat_map(|y| (0..WIDTH).map(move |x| ((x / 8 + y / 8) % 256) as u8))
+        .collect()
+}
+
+/// A frame made of random colors, barely compressing.
+fn noise() -> Vec<u8> {
+    // Linear congruential generator, good enough here
+    let mut state: u32 = 1;
+    (0..WIDTH * HEIGHT)
+        .map(|_| {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            (state >> 16) as u8
+        })
+        .collect()
+}
+
+/// Compress the given color indices as they would be in a GIF buffer.
+fn compress(indices: &[u8], min_code_size: u8) -> Vec<u8> {
+    let clear_code = 1u16 << min_code_size;
+    let mut out = vec![];
+    let mut acc: u32 = 0;
+    let mut nb_bits = 0;
+    let mut code_size = min_code_size + 1;
+    let mut emit = |code: u16, code_size: u8| {
+        acc |= (code as u32) << nb_bits;
+        nb_bits += code_size;
+        while nb_bits >= 8 {
+            out.push(acc as u8);
+            acc >>= 8;
+            nb_bits -= 8;
+        }
+    };
+
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut next_code = clear_code + 2;
+    emit(clear_code, code_size);
+    let mut current: Option<u16> = None;
+    for &index in indices {
+        let prefix = match current {
+            None => {
+                current = Some(index as u16);
+                continue;
+            }
+            Some(prefix) => prefix,
+        };
+        if let Some(&code) = table.get(&(prefix, index)) {
+            current = Some(code);
+            continue;
+        }
+        emit(prefix, code_size);
+        if next_code < 4096 {
+            table.insert((prefix, index), next_code);
+            next_code += 1;
+            if next_code > 1 << code_size && code_size < 12 {
+                code_size += 1;
+            }
+        } else {
+            emit(clear_code, code_size);
+            table.clear();
+            next_code = clear_code + 2;
+            code_size = min_code_size + 1;
+        }
+        current = Some(index as u16);
+    }
+    if let Some(code) = current {
+        emit(code, code_size);
+    }
+    emit(clear_code + 1, code_size);
+    if nb_bits > 0 {
+        out.push(acc as u8);
+    }
+    out
+}
+
+criterion_group!(benches, bench_lzw);
+criterion_main!(benches);
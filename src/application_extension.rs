@@ -0,0 +1,237 @@
+use crate::error::{GifParsingError, Result};
+use crate::gif_reader::GifRead;
+
+/// Identifier of the Netscape Application Extension, defining looping.
+pub(crate) const NETSCAPE_IDENTIFIER: &str = "NETSCAPE2.0";
+
+/// Identifier of the AnimExts Application Extension, equivalent to the
+/// Netscape one.
+pub(crate) const ANIMEXTS_IDENTIFIER: &str = "ANIMEXTS1.0";
+
+/// Identifier of the Application Extension containing an XMP packet.
+pub(crate) const XMP_IDENTIFIER: &str = "XMP DataXMP";
+
+/// Identifier of the Application Extension containing an ICC color profile.
+pub(crate) const ICC_IDENTIFIER: &str = "ICCRGBG1012";
+
+/// Length of the "magic trailer" ending XMP Application Extensions, block
+/// terminator excluded.
+const XMP_MAGIC_TRAILER_LEN: usize = 257;
+
+/// An Application Extension found in a GIF buffer, as given to the handlers
+/// registered on a decoder (see `GifDecoder::register_application_handler`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApplicationExtension {
+    /// Position in the GIF buffer of the extension's Extension Introducer.
+    pub position: usize,
+
+    /// Application identifier followed by its authentication code (e.g.
+    /// `"NETSCAPE2.0"`).
+    /// Bytes which are not valid UTF-8 are replaced by `U+FFFD`.
+    pub identifier: String,
+
+    /// Application data, which is the data of every sub-block put one after
+    /// the other.
+    ///
+    /// XMP packets (`"XMP DataXMP"`) are an exception, as they are not split
+    /// into sub-blocks: their data is the packet as stored, without the
+    /// "magic trailer" following it.
+    pub data: Vec<u8>,
+}
+
+/// Function called with every Application Extension having a given
+/// identifier.
+type ApplicationHandler = Box<dyn FnMut(&ApplicationExtension) + Send>;
+
+/// Handlers registered for Application Extensions, by identifier.
+#[derive(Default)]
+pub(crate) struct ApplicationExtensionRegistry {
+    handlers: Vec<(String, ApplicationHandler)>,
+}
+
+impl ApplicationExtensionRegistry {
+    /// Register a handler, called with every Application Extension whose
+    /// identifier (authentication code included) is `identifier`.
+    pub(crate) fn register(&mut self, identifier: &str, handler: ApplicationHandler) {
+        self.handlers.push((identifier.to_owned(), handler));
+    }
+
+    /// Returns `true` if at least one handler is registered for the given
+    /// identifier.
+    pub(crate) fn has_handler(&self, identifier: &str) -> bool {
+        self.handlers.iter().any(|(id, _)| id == identifier)
+    }
+
+    /// Call every handler registered for the given Application Extension.
+    pub(crate) fn handle(&mut self, extension: &ApplicationExtension) {
+        for (id, handler) in self.handlers.iter_mut() {
+            if *id == extension.identifier {
+                handler(extension);
+            }
+        }
+    }
+}
+
+/// Parse the start of an Application Extension (the `rdr` should be just after
+/// its label), until its application data, and return its identifier
+/// followed by its authentication code.
+pub(crate) fn parse_application_identifier(rdr: &mut impl GifRead) -> Result<String> {
+    let block_size = rdr.read_u8()?;
+    if block_size != 11 {
+        return Err(GifParsingError::UnexpectedLength {
+            block_name: "Application Extension".to_owned(),
+            expected: 11,
+            got: block_size,
+        });
+    }
+    let identifier = rdr.read_bytes(11)?;
+    Ok(String::from_utf8_lossy(&identifier).into_owned())
+}
+
+/// Read the application data of an Application Extension, sub-block by
+/// sub-block, until the block terminator.
+pub(crate) fn read_application_data(rdr: &mut impl GifRead) -> Result<Vec<Vec<u8>>> {
+    let mut sub_blocks = vec![];
+    loop {
+        let size_of_block = rdr.read_u8()? as usize;
+        if size_of_block == 0 {
+            return Ok(sub_blocks);
+        }
+        sub_blocks.push(rdr.read_bytes(size_of_block)?);
+    }
+}
+
+/// Rebuild the XMP packet contained in the given sub-blocks of an XMP
+/// Application Extension.
+///
+/// XMP packets are written as is, followed by a "magic trailer" which makes
+/// them readable as sub-blocks: the packet is found back by putting the size
+/// bytes back in between them and by removing that trailer.
+pub(crate) fn xmp_packet(sub_blocks: &[Vec<u8>]) -> Vec<u8> {
+    let mut packet = vec![];
+    for sub_block in sub_blocks {
+        packet.push(sub_block.len() as u8);
+        packet.extend_from_slice(sub_block);
+    }
+    if packet.len() >= XMP_MAGIC_TRAILER_LEN {
+        let trailer_start = packet.len() - XMP_MAGIC_TRAILER_LEN;
+        let trailer = &packet[trailer_start..];
+        // 0x01, 0xFF, 0xFE, ..., 0x01, 0x00
+        let is_magic_trailer =
+            trailer[0] == 0x01 && trailer[1..].iter().rev().copied().eq(0x00..=0xFF);
+        if is_magic_trailer {
+            packet.truncate(trailer_start);
+        }
+    }
+    packet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gif_decoder::{decode_from_bytes, GifDecoder};
+    use crate::gif_reader::SliceReader;
+    use crate::options::DecodeOptions;
+    use crate::test_utils::GifBuilder;
+    use crate::warning::Warning;
+    use std::sync::{Arc, Mutex};
+
+    /// Magic trailer following XMP packets.
+    fn xmp_magic_trailer() -> Vec<u8> {
+        let mut trailer = vec![0x01];
+        trailer.extend((0x00..=0xFF).rev());
+        trailer
+    }
+
+    /// Returns the application data of an XMP Application Extension made of
+    /// `packet` followed by `trailer`, as read from a GIF buffer.
+    fn read_xmp_sub_blocks(packet: &[u8], trailer: &[u8]) -> Vec<Vec<u8>> {
+        let mut data = packet.to_vec();
+        data.extend_from_slice(trailer);
+        data.push(0x00);
+        let mut rdr = SliceReader::new(&data);
+        let sub_blocks = read_application_data(&mut rdr).unwrap();
+        assert_eq!(rdr.get_pos(), data.len());
+        sub_blocks
+    }
+
+    #[test]
+    fn calls_handlers_registered_for_identifier() {
+        let gif = GifBuilder::new(1, 1, 0)
+            .application_extension(b"GIFRENDR1.0", &[b"abc", b"de"])
+            .application_extension(b"OTHERAPP1.0", &[b"fgh"])
+            .image(0, 0, 1, 1, &[1])
+            .build();
+        let received = Arc::new(Mutex::new(vec![]));
+        let mut decoder = decode_from_bytes(&gif).unwrap();
+        let handler_received = Arc::clone(&received);
+        decoder.register_application_handler("GIFRENDR1.0", move |extension| {
+            handler_received.lock().unwrap().push(extension.clone());
+        });
+        assert!(decoder.next_frame().unwrap().is_some());
+        assert_eq!(
+            *received.lock().unwrap(),
+            [ApplicationExtension {
+                position: 25,
+                identifier: "GIFRENDR1.0".to_owned(),
+                data: b"abcde".to_vec(),
+            }]
+        );
+        // Unknown extensions without handler are silently skipped
+        assert!(decoder.warnings().is_empty());
+    }
+
+    #[test]
+    fn reports_ignored_extensions_when_strict() {
+        let gif = GifBuilder::new(1, 1, 0)
+            .application_extension(b"OTHERAPP1.0", &[b"fgh"])
+            .image(0, 0, 1, 1, &[1])
+            .build();
+        let options = DecodeOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let mut decoder = GifDecoder::with_options(SliceReader::new(&gif), options).unwrap();
+        assert!(decoder.next_frame().unwrap().is_some());
+        assert_eq!(
+            decoder.warnings(),
+            [Warning::IgnoredApplicationExtension {
+                identifier: "OTHERAPP1.0".to_owned(),
+                position: 25,
+            }]
+        );
+    }
+
+    #[test]
+    fn reports_netscape_buffering_sub_block() {
+        let gif = GifBuilder::new(1, 1, 0)
+            .application_extension(
+                b"ANIMEXTS1.0",
+                &[&[0x01, 0x02, 0x00], &[0x02, 0x00, 0x10, 0, 0]],
+            )
+            .image(0, 0, 1, 1, &[1])
+            .build();
+        let mut decoder = decode_from_bytes(&gif).unwrap();
+        assert!(decoder.next_frame().unwrap().is_some());
+        assert_eq!(decoder.loop_count(), Some(2));
+        assert_eq!(decoder.buffer_size(), Some(4096));
+    }
+
+    #[test]
+    fn rebuilds_xmp_packet() {
+        let packet = b"<?xpacket begin=\"\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\
+            <x:xmpmeta xmlns:x=\"adobe:ns:meta/\"/><?xpacket end=\"r\"?>";
+        let sub_blocks = read_xmp_sub_blocks(packet, &xmp_magic_trailer());
+        assert_eq!(xmp_packet(&sub_blocks), packet);
+    }
+
+    #[test]
+    fn keeps_xmp_packet_with_truncated_trailer() {
+        let packet = b"<x:xmpmeta xmlns:x=\"adobe:ns:meta/\"/>";
+        let trailer = &xmp_magic_trailer()[1..];
+        let sub_blocks = read_xmp_sub_blocks(packet, trailer);
+        let mut expected = packet.to_vec();
+        expected.extend_from_slice(trailer);
+        assert_eq!(xmp_packet(&sub_blocks), expected);
+    }
+}
@@ -3,30 +3,91 @@ use crate::gif_reader::GifRead;
 
 /// Simple structure containing "RGB" (Red Green Blue) colors as defined in a
 /// GIF's color table.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Rgb {
-    r: u8,
-    g: u8,
-    b: u8,
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
 }
 
-impl From<&Rgb> for u32 {
-    fn from(val: &Rgb) -> u32 {
-        (*val).into()
-    }
+/// Layout of the pixels in a decoded frame.
+///
+/// Every format is defined as a sequence of bytes, so the layout does not
+/// depend on the endianness of the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PixelFormat {
+    /// 4 bytes per pixel: red, green, blue then alpha.
+    #[default]
+    Rgba8,
+
+    /// 4 bytes per pixel: blue, green, red then alpha.
+    Bgra8,
+
+    /// 3 bytes per pixel: red, green then blue.
+    /// As there is no alpha channel, transparent pixels always have the
+    /// background color.
+    Rgb8,
+
+    /// 4 bytes per pixel: red, green, blue then alpha, with each color
+    /// component already multiplied by the alpha.
+    RgbaPremultiplied,
+
+    /// 2 bytes per pixel: a 16 bits little-endian value with 5 bits of red
+    /// (most significant), 6 bits of green then 5 bits of blue.
+    /// As there is no alpha channel, transparent pixels always have the
+    /// background color.
+    Rgb565,
+
+    /// 1 byte per pixel: the index of the pixel's color in the color table,
+    /// which is communicated alongside the frame.
+    /// Pixels which have never been painted (or which have been restored to the
+    /// background) contain the background color index defined in the GIF's
+    /// header.
+    Indexed,
 }
 
-impl From<Rgb> for u32 {
-    fn from(val: Rgb) -> Self {
-        // Into a reverse ABGR order as it seems that's what the current openGL
+impl PixelFormat {
+    /// Number of bytes taken by a single pixel in that format.
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            PixelFormat::Rgba8 | PixelFormat::Bgra8 | PixelFormat::RgbaPremultiplied => 4,
+            PixelFormat::Rgb8 => 3,
+            PixelFormat::Rgb565 => 2,
+            PixelFormat::Indexed => 1,
+        }
+    }
 
-        0xFF000000 + ((val.b as u32) << 16) + ((val.g as u32) << 8) + (val.r as u32)
+    /// Returns `true` if that format can represent transparent pixels.
+    pub fn has_alpha(self) -> bool {
+        match self {
+            PixelFormat::Rgba8 | PixelFormat::Bgra8 | PixelFormat::RgbaPremultiplied => true,
+            PixelFormat::Rgb8 | PixelFormat::Rgb565 | PixelFormat::Indexed => false,
+        }
+    }
+
+    /// Encode an opaque color, whose index in its color table is `index`, in
+    /// that format.
+    /// Only the first `bytes_per_pixel` bytes of the result are relevant.
+    pub(crate) fn encode(self, color: Rgb, index: u8) -> [u8; 4] {
+        let Rgb { r, g, b } = color;
+        match self {
+            PixelFormat::Rgba8 | PixelFormat::RgbaPremultiplied => [r, g, b, 0xFF],
+            PixelFormat::Bgra8 => [b, g, r, 0xFF],
+            PixelFormat::Rgb8 => [r, g, b, 0],
+            PixelFormat::Rgb565 => {
+                let val: u16 = ((r as u16 >> 3) << 11) | ((g as u16 >> 2) << 5) | (b as u16 >> 3);
+                let [lo, hi] = val.to_le_bytes();
+                [lo, hi, 0, 0]
+            }
+            PixelFormat::Indexed => [index, 0, 0, 0],
+        }
     }
 }
 
 /// Parse color table from a GIF file (the `rdr` should currently be just at the
 /// start of the first element in that color table) into the corresponding
 /// vector of RGB values.
+/// Like in any GIF file, that table cannot have more than 256 entries.
 pub fn parse_color_table(rdr: &mut impl GifRead, nb_entries: usize) -> Result<Vec<Rgb>> {
     // NOTE: Old implem, I prefer relying on safe rust now
     //
@@ -40,14 +101,15 @@ pub fn parse_color_table(rdr: &mut impl GifRead, nb_entries: usize) -> Result<Ve
     //     Vec::from_raw_parts(ptr, len, cap)
     // };
     // Ok(ct)
-    let mut ct: Vec<Rgb> = vec![Rgb { r: 0, g: 0, b: 0 }; nb_entries];
-    for item in ct.iter_mut() {
-        let colors = rdr.read_bytes(3)?;
-        *item = Rgb {
+    let mut buf = [0; 3 * 256];
+    let raw_color_table = rdr.read_borrowed(&mut buf[..3 * nb_entries])?;
+    let ct = raw_color_table
+        .chunks_exact(3)
+        .map(|colors| Rgb {
             r: colors[0],
             g: colors[1],
             b: colors[2],
-        };
-    }
+        })
+        .collect();
     Ok(ct)
 }
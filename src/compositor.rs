@@ -0,0 +1,751 @@
+use crate::color::{PixelFormat, Rgb};
+use crate::options::{DecodeOptions, TransparencyMode};
+use crate::parser::{self, DisposalMethod, Frame, FrameInfo, GifHeader, RawFrame, RowSink};
+
+/// Background color used when none is defined.
+const DEFAULT_BACKGROUND_COLOR: Rgb = Rgb {
+    r: 0xFF,
+    g: 0xFF,
+    b: 0xFF,
+};
+
+/// Render successive raw frames on a canvas the size of the GIF's logical
+/// screen, applying each frame's disposal method as defined by the GIF89a
+/// specification:
+///
+///   - `NoDisposalSpecified` and `DoNotDispose`: the frame is left in place.
+///
+///   - `RestoreToBackgroundColor`: the rectangle used by the frame - and only
+///     it - is restored to the background color (or to transparent pixels if
+///     alpha is kept) before the next frame is rendered.
+///
+///   - `RestoreToPrevious`: the rectangle used by the frame is restored to
+///     what it contained before that frame was rendered.
+///
+/// Only the rectangle of each frame is touched when rendering and disposing
+/// of it, so that the work needed scales with the frame's size rather than
+/// with the size of the logical screen. When decoding, frames are drawn row by
+/// row as their image data is decompressed, without being stored first.
+///
+/// Frames given to the `Compositor` do not need to come from a `GifDecoder`,
+/// they just have to be given in the order they should be displayed.
+pub struct Compositor {
+    /// Width of the canvas, in pixels.
+    width: usize,
+
+    /// Height of the canvas, in pixels.
+    height: usize,
+
+    /// Layout of the pixels in the canvas.
+    pixel_format: PixelFormat,
+
+    /// Value, in `pixel_format`, the canvas is initially filled with and
+    /// restored to by the `RestoreToBackgroundColor` disposal method.
+    /// Fully transparent when alpha is kept.
+    background: [u8; 4],
+
+    /// Index in the Global Color Table of the background color, used by the
+    /// `PixelFormat::Indexed` format.
+    background_index: u8,
+
+    /// If `true`, the background is fully transparent, whatever its color.
+    transparent_background: bool,
+
+    /// Set to `true` once a frame has been rendered on the canvas, and back to
+    /// `false` when it is reset.
+    is_painted: bool,
+
+    /// Pixels of the current canvas, row by row, in `pixel_format`.
+    canvas: Vec<u8>,
+
+    /// Width, in pixels, of the frames returned by `render_frame`, which
+    /// differs from `width` if they are resampled to square pixels.
+    output_width: usize,
+
+    /// Height, in pixels, of the frames returned by `render_frame`, which
+    /// differs from `height` if they are resampled to square pixels.
+    output_height: usize,
+
+    /// Disposal of the last rendered frame, which has to be applied before
+    /// rendering the next one.
+    pending_disposal: Option<PendingDisposal>,
+
+    /// Pixels of the rectangle of the last rendered frame, as they were before
+    /// it was rendered, if its disposal method is `RestoreToPrevious`.
+    /// Kept in between frames to avoid allocating.
+    previous_pixels: Vec<u8>,
+
+    /// Top-left corner of the rectangle of the frame being rendered, in the
+    /// canvas.
+    frame_origin: (usize, usize),
+
+    /// Part of the canvas covered by the frame being rendered.
+    frame_rect: Rect,
+
+    /// Part of the canvas which may have changed since the last `Frame` was
+    /// output, so that only it has to be copied to update that `Frame`.
+    changed_rect: Rect,
+
+    /// Each color of the frame being rendered, in `pixel_format`, by index in
+    /// its color table. `None` for the transparent color index and the indices
+    /// out of its color table, which leave pixels unchanged.
+    frame_colors: Box<[Option<[u8; 4]>; 256]>,
+}
+
+/// Disposal which should be applied before rendering the next frame.
+enum PendingDisposal {
+    /// Fill the given rectangle with the background color.
+    Background(Rect),
+
+    /// Put back the pixels in `previous_pixels`, which were in the given
+    /// rectangle before the last frame was rendered.
+    Previous(Rect),
+}
+
+/// A rectangle of the canvas, already clipped to its dimensions.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+struct Rect {
+    left: usize,
+    top: usize,
+    width: usize,
+    height: usize,
+}
+
+impl Compositor {
+    /// Create a new `Compositor` for a logical screen of the given
+    /// dimensions.
+    /// The canvas is initially filled with `background_color` (white if `None`)
+    /// or left fully transparent, depending on the `TransparencyMode` and the
+    /// `PixelFormat` from `options`.
+    pub fn new(
+        width: u16,
+        height: u16,
+        background_color: Option<Rgb>,
+        options: &DecodeOptions,
+    ) -> Compositor {
+        Compositor::with_background_index(width, height, background_color, 0, None, options)
+    }
+
+    /// Create a new `Compositor` for the GIF whose header is given, using the
+    /// background color it defines.
+    /// If `options.square_pixels` is set, rendered frames are resampled
+    /// according to the pixel aspect ratio it declares.
+    pub fn from_header(header: &GifHeader, options: &DecodeOptions) -> Compositor {
+        Compositor::with_background_index(
+            header.width,
+            header.height,
+            header.background_color(),
+            header.background_color_index,
+            header.pixel_aspect_ratio(),
+            options,
+        )
+    }
+
+    fn with_background_index(
+        width: u16,
+        height: u16,
+        background_color: Option<Rgb>,
+        background_index: u8,
+        pixel_aspect_ratio: Option<f32>,
+        options: &DecodeOptions,
+    ) -> Compositor {
+        let pixel_format = options.pixel_format;
+        let transparent_background =
+            options.transparency == TransparencyMode::KeepAlpha && pixel_format.has_alpha();
+        let background = if transparent_background {
+            [0; 4]
+        } else {
+            let color = background_color.unwrap_or(DEFAULT_BACKGROUND_COLOR);
+            pixel_format.encode(color, background_index)
+        };
+        let width = width as usize;
+        let height = height as usize;
+        let bpp = pixel_format.bytes_per_pixel();
+        let canvas = background[..bpp].repeat(width * height);
+        let (output_width, output_height) = match pixel_aspect_ratio {
+            Some(ratio) if options.square_pixels => square_pixels_size(width, height, ratio),
+            _ => (width, height),
+        };
+        Compositor {
+            width,
+            height,
+            pixel_format,
+            background,
+            background_index,
+            transparent_background,
+            is_painted: false,
+            canvas,
+            output_width,
+            output_height,
+            pending_disposal: None,
+            previous_pixels: vec![],
+            frame_origin: (0, 0),
+            frame_rect: Rect::default(),
+            changed_rect: Rect {
+                left: 0,
+                top: 0,
+                width,
+                height,
+            },
+            frame_colors: Box::new([None; 256]),
+        }
+    }
+
+    /// Layout of the pixels in the canvas.
+    pub fn pixel_format(&self) -> PixelFormat {
+        self.pixel_format
+    }
+
+    /// Returns the current content of the canvas, row by row, in the
+    /// `PixelFormat` asked for.
+    pub fn canvas(&self) -> &[u8] {
+        &self.canvas
+    }
+
+    /// Render the given frame on the canvas, after disposing of the previous
+    /// one, and return the resulting canvas.
+    ///
+    /// Pixels missing from the frame (see `RawFrame::nb_decoded`) are left
+    /// untouched.
+    pub fn composite(&mut self, frame: &RawFrame) -> &[u8] {
+        self.begin_frame(&frame.info, &frame.palette);
+        let width = frame.info.width as usize;
+        if width == 0 {
+            return &self.canvas;
+        }
+        if frame.nb_decoded == frame.indices.len() {
+            for (y, row) in frame.indices.chunks_exact(width).enumerate() {
+                self.put_row(y, row);
+            }
+        } else {
+            let rows = parser::row_order(frame.info.height as usize, frame.info.interlaced);
+            for (nb_rows, y) in rows.into_iter().enumerate() {
+                let nb_pixels = frame.nb_decoded.saturating_sub(nb_rows * width);
+                if nb_pixels == 0 {
+                    break;
+                }
+                let row = &frame.indices[y * width..(y + 1) * width];
+                self.put_partial_row(y, row, nb_pixels.min(width));
+            }
+        }
+        &self.canvas
+    }
+
+    /// Dispose of the previous frame and prepare the rendering of the frame
+    /// described by `info`, using the color table `palette`, whose rows can
+    /// then be given through `RowSink::put_row`.
+    pub(crate) fn begin_frame(&mut self, info: &FrameInfo, palette: &[Rgb]) {
+        self.apply_pending_disposal();
+        self.is_painted = true;
+
+        let rect = self.clip(info.left, info.top, info.width, info.height);
+        self.pending_disposal = match info.disposal_method {
+            DisposalMethod::NoDisposalSpecified | DisposalMethod::DoNotDispose => None,
+            DisposalMethod::RestoreToBackgroundColor => Some(PendingDisposal::Background(rect)),
+            DisposalMethod::RestoreToPrevious => {
+                self.copy_rect_to_previous_pixels(rect);
+                Some(PendingDisposal::Previous(rect))
+            }
+        };
+        self.frame_origin = (info.left as usize, info.top as usize);
+        self.frame_rect = rect;
+        self.changed_rect = self.changed_rect.union(rect);
+
+        // Encode the whole color table once, so each pixel is just a copy
+        let pixel_format = self.pixel_format;
+        for (i, color) in self.frame_colors.iter_mut().enumerate() {
+            *color = match palette.get(i) {
+                Some(_) if Some(i as u8) == info.transparent_color_index => None,
+                Some(rgb) => Some(pixel_format.encode(*rgb, i as u8)),
+                None => None,
+            };
+        }
+    }
+
+    /// Render the given frame on the canvas, like `composite`, and return a
+    /// `Frame` containing a copy of the resulting canvas.
+    pub(crate) fn render_frame(&mut self, raw_frame: RawFrame) -> Frame {
+        self.composite(&raw_frame);
+        self.output_frame(raw_frame.info, raw_frame.delay, raw_frame.palette)
+    }
+
+    /// Returns a `Frame` containing a copy of the canvas, once the frame
+    /// described by the given information has been rendered on it.
+    pub(crate) fn output_frame(
+        &mut self,
+        info: FrameInfo,
+        delay: Option<u16>,
+        palette: Vec<Rgb>,
+    ) -> Frame {
+        let mut data = vec![];
+        self.copy_canvas_to(&mut data);
+        Frame {
+            data,
+            width: self.output_width,
+            height: self.output_height,
+            palette: self.output_palette(palette),
+            delay,
+            info,
+        }
+    }
+
+    /// Turn `frame`, the last `Frame` output, into the `Frame` `output_frame`
+    /// would return, reusing its buffer: only the part of the canvas which
+    /// changed since is copied to it.
+    pub(crate) fn update_frame(
+        &mut self,
+        frame: &mut Frame,
+        info: FrameInfo,
+        delay: Option<u16>,
+        palette: Vec<Rgb>,
+    ) {
+        self.copy_canvas_to(&mut frame.data);
+        frame.width = self.output_width;
+        frame.height = self.output_height;
+        frame.palette = self.output_palette(palette);
+        frame.delay = delay;
+        frame.info = info;
+    }
+
+    /// Returns the color table to communicate along with a frame using
+    /// `palette`.
+    fn output_palette(&self, palette: Vec<Rgb>) -> Option<Vec<Rgb>> {
+        match self.pixel_format {
+            PixelFormat::Indexed => Some(palette),
+            _ => None,
+        }
+    }
+
+    /// Turn `data`, a copy of the canvas as it was when last output (or
+    /// anything else if its size differs), into a copy of the current canvas,
+    /// scaled to the output dimensions.
+    /// Only the part of the canvas which changed since is copied, unless
+    /// frames are resampled to square pixels.
+    fn copy_canvas_to(&mut self, data: &mut Vec<u8>) {
+        let rect = std::mem::take(&mut self.changed_rect);
+        if (self.output_width, self.output_height) != (self.width, self.height) {
+            data.clear();
+            self.resample_canvas_into(data);
+        } else if data.len() != self.canvas.len() {
+            data.clear();
+            data.extend_from_slice(&self.canvas);
+        } else {
+            let bpp = self.pixel_format.bytes_per_pixel();
+            for y in rect.top..rect.top + rect.height {
+                let start = (y * self.width + rect.left) * bpp;
+                let end = start + rect.width * bpp;
+                data[start..end].copy_from_slice(&self.canvas[start..end]);
+            }
+        }
+    }
+
+    /// Reset the canvas to its initial state, as if no frame had been
+    /// rendered yet.
+    pub fn reset(&mut self) {
+        self.pending_disposal = None;
+        self.is_painted = false;
+        self.changed_rect = Rect {
+            left: 0,
+            top: 0,
+            width: self.width,
+            height: self.height,
+        };
+        let bpp = self.pixel_format.bytes_per_pixel();
+        for pixel in self.canvas.chunks_exact_mut(bpp) {
+            pixel.copy_from_slice(&self.background[..bpp]);
+        }
+    }
+
+    /// Change the background color (white if `None`), for example once it has
+    /// been converted to another color space.
+    ///
+    /// If no frame has been rendered yet, the whole canvas is filled with it.
+    /// If not, only the pixels restored to the background color from now on
+    /// will have it.
+    pub fn set_background_color(&mut self, background_color: Option<Rgb>) {
+        if self.transparent_background {
+            return;
+        }
+        let color = background_color.unwrap_or(DEFAULT_BACKGROUND_COLOR);
+        self.background = self.pixel_format.encode(color, self.background_index);
+        if !self.is_painted {
+            self.reset();
+        }
+    }
+
+    /// Apply the disposal method of the last rendered frame, if not already
+    /// done.
+    fn apply_pending_disposal(&mut self) {
+        let bpp = self.pixel_format.bytes_per_pixel();
+        match self.pending_disposal.take() {
+            None => {}
+            Some(PendingDisposal::Background(rect)) => {
+                self.changed_rect = self.changed_rect.union(rect);
+                let background = &self.background[..bpp];
+                for y in rect.top..rect.top + rect.height {
+                    let start = (y * self.width + rect.left) * bpp;
+                    let row = &mut self.canvas[start..start + rect.width * bpp];
+                    for pixel in row.chunks_exact_mut(bpp) {
+                        pixel.copy_from_slice(background);
+                    }
+                }
+            }
+            Some(PendingDisposal::Previous(rect)) if rect.width == 0 => {}
+            Some(PendingDisposal::Previous(rect)) => {
+                self.changed_rect = self.changed_rect.union(rect);
+                let rows = self.previous_pixels.chunks_exact(rect.width * bpp);
+                for (y, row) in rows.enumerate() {
+                    let start = ((rect.top + y) * self.width + rect.left) * bpp;
+                    self.canvas[start..start + rect.width * bpp].copy_from_slice(row);
+                }
+            }
+        }
+    }
+
+    /// Push to `data` the canvas scaled to the output dimensions, each pixel
+    /// taking the value of the nearest one in the canvas.
+    fn resample_canvas_into(&self, data: &mut Vec<u8>) {
+        let bpp = self.pixel_format.bytes_per_pixel();
+        let src_columns: Vec<usize> = (0..self.output_width)
+            .map(|x| x * self.width / self.output_width)
+            .collect();
+        data.reserve(self.output_width * self.output_height * bpp);
+        for y in 0..self.output_height {
+            let src_y = y * self.height / self.output_height;
+            let src_row = &self.canvas[src_y * self.width * bpp..(src_y + 1) * self.width * bpp];
+            for &src_x in &src_columns {
+                data.extend_from_slice(&src_row[src_x * bpp..(src_x + 1) * bpp]);
+            }
+        }
+    }
+
+    /// Copy the pixels currently in the given rectangle of the canvas to
+    /// `previous_pixels`.
+    fn copy_rect_to_previous_pixels(&mut self, rect: Rect) {
+        let bpp = self.pixel_format.bytes_per_pixel();
+        self.previous_pixels.clear();
+        for y in rect.top..rect.top + rect.height {
+            let start = (y * self.width + rect.left) * bpp;
+            self.previous_pixels
+                .extend_from_slice(&self.canvas[start..start + rect.width * bpp]);
+        }
+    }
+
+    /// Clip the given rectangle to the dimensions of the canvas.
+    fn clip(&self, left: u16, top: u16, width: u16, height: u16) -> Rect {
+        let left = (left as usize).min(self.width);
+        let top = (top as usize).min(self.height);
+        let right = (left + width as usize).min(self.width);
+        let bottom = (top + height as usize).min(self.height);
+        Rect {
+            left,
+            top,
+            width: right - left,
+            height: bottom - top,
+        }
+    }
+}
+
+impl Rect {
+    /// Returns the smallest rectangle containing both `self` and `other`.
+    fn union(self, other: Rect) -> Rect {
+        if other.width == 0 || other.height == 0 {
+            return self;
+        }
+        if self.width == 0 || self.height == 0 {
+            return other;
+        }
+        let left = self.left.min(other.left);
+        let top = self.top.min(other.top);
+        let right = (self.left + self.width).max(other.left + other.width);
+        let bottom = (self.top + self.height).max(other.top + other.height);
+        Rect {
+            left,
+            top,
+            width: right - left,
+            height: bottom - top,
+        }
+    }
+}
+
+/// The rows of the frame being rendered, drawn on the canvas as they are given.
+impl RowSink for Compositor {
+    fn put_partial_row(&mut self, y: usize, row: &[u8], nb_pixels: usize) {
+        let rect = self.frame_rect;
+        let canvas_y = self.frame_origin.1 + y;
+        if rect.width == 0 || canvas_y >= rect.top + rect.height {
+            return;
+        }
+        // The rectangle is only clipped on its right and bottom edges
+        let offset = rect.left - self.frame_origin.0;
+        let width = nb_pixels.saturating_sub(offset).min(rect.width);
+        let src = &row[offset..][..width];
+        let bpp = self.pixel_format.bytes_per_pixel();
+        let start = (canvas_y * self.width + rect.left) * bpp;
+        let dst = &mut self.canvas[start..start + width * bpp];
+        let colors = &self.frame_colors;
+        match bpp {
+            1 => expand_row::<1>(dst, src, colors),
+            2 => expand_row::<2>(dst, src, colors),
+            3 => expand_row::<3>(dst, src, colors),
+            _ => expand_row::<4>(dst, src, colors),
+        }
+    }
+}
+
+/// Write to `dst` the colors, of `BPP` bytes each, of the color indices in
+/// `src`, leaving the pixels whose color is `None` unchanged.
+#[inline]
+fn expand_row<const BPP: usize>(dst: &mut [u8], src: &[u8], colors: &[Option<[u8; 4]>; 256]) {
+    for (dst, &idx) in dst.chunks_exact_mut(BPP).zip(src) {
+        if let Some(color) = &colors[idx as usize] {
+            dst.copy_from_slice(&color[..BPP]);
+        }
+    }
+}
+
+/// Returns the dimensions a canvas of `width` x `height` pixels, whose pixels
+/// have the given aspect ratio (width divided by height), should have to be
+/// displayed with square pixels.
+/// The canvas is only ever enlarged, so that no pixel is lost.
+pub(crate) fn square_pixels_size(
+    width: usize,
+    height: usize,
+    pixel_aspect_ratio: f32,
+) -> (usize, usize) {
+    if pixel_aspect_ratio >= 1. {
+        let width = (width as f32 * pixel_aspect_ratio).round() as usize;
+        (width, height)
+    } else {
+        let height = (height as f32 / pixel_aspect_ratio).round() as usize;
+        (width, height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gif_decoder::GifDecoder;
+    use crate::gif_reader::SliceReader;
+    use crate::test_utils::{GifBuilder, PALETTE};
+
+    /// Build a frame using `PALETTE`, with the given rectangle and disposal
+    /// method.
+    fn raw_frame(
+        (left, top, width, height): (u16, u16, u16, u16),
+        disposal_method: DisposalMethod,
+        transparent_color_index: Option<u8>,
+        indices: &[u8],
+    ) -> RawFrame {
+        RawFrame {
+            indices: indices.to_vec(),
+            nb_decoded: indices.len(),
+            palette: PALETTE.to_vec(),
+            delay: None,
+            info: FrameInfo {
+                position: 0,
+                left,
+                top,
+                width,
+                height,
+                disposal_method,
+                transparent_color_index,
+                user_input: false,
+                interlaced: false,
+                has_local_color_table: false,
+                plain_text: None,
+            },
+        }
+    }
+
+    /// `Compositor` for a 3x2 logical screen, rendering color indices.
+    fn indexed_compositor() -> Compositor {
+        let options = DecodeOptions {
+            pixel_format: PixelFormat::Indexed,
+            ..Default::default()
+        };
+        Compositor::new(3, 2, None, &options)
+    }
+
+    #[test]
+    fn keeps_frames_not_disposed() {
+        let mut compositor = indexed_compositor();
+        let full = raw_frame((0, 0, 3, 2), DisposalMethod::DoNotDispose, None, &[1; 6]);
+        compositor.composite(&full);
+        let pixel = raw_frame(
+            (1, 1, 1, 1),
+            DisposalMethod::NoDisposalSpecified,
+            None,
+            &[2],
+        );
+        assert_eq!(compositor.composite(&pixel), [1, 1, 1, 1, 2, 1]);
+        let pixel = raw_frame((0, 0, 1, 1), DisposalMethod::DoNotDispose, None, &[3]);
+        assert_eq!(compositor.composite(&pixel), [3, 1, 1, 1, 2, 1]);
+    }
+
+    #[test]
+    fn restores_frame_rectangle_to_background() {
+        let mut compositor = indexed_compositor();
+        let full = raw_frame((0, 0, 3, 2), DisposalMethod::DoNotDispose, None, &[1; 6]);
+        compositor.composite(&full);
+        let row = raw_frame(
+            (0, 0, 2, 1),
+            DisposalMethod::RestoreToBackgroundColor,
+            None,
+            &[2, 2],
+        );
+        // Disposal only happens before the next frame
+        assert_eq!(compositor.composite(&row), [2, 2, 1, 1, 1, 1]);
+        let pixel = raw_frame((2, 1, 1, 1), DisposalMethod::DoNotDispose, None, &[3]);
+        assert_eq!(compositor.composite(&pixel), [0, 0, 1, 1, 1, 3]);
+    }
+
+    #[test]
+    fn restores_frame_rectangle_to_previous() {
+        let mut compositor = indexed_compositor();
+        let full = raw_frame((0, 0, 3, 2), DisposalMethod::DoNotDispose, None, &[1; 6]);
+        compositor.composite(&full);
+        let row = raw_frame(
+            (1, 0, 2, 1),
+            DisposalMethod::RestoreToPrevious,
+            None,
+            &[2, 2],
+        );
+        assert_eq!(compositor.composite(&row), [1, 2, 2, 1, 1, 1]);
+        let pixel = raw_frame((0, 1, 1, 1), DisposalMethod::DoNotDispose, None, &[3]);
+        assert_eq!(compositor.composite(&pixel), [1, 1, 1, 3, 1, 1]);
+    }
+
+    #[test]
+    fn leaves_transparent_pixels_unchanged() {
+        let mut compositor = indexed_compositor();
+        let full = raw_frame((0, 0, 3, 2), DisposalMethod::DoNotDispose, None, &[1; 6]);
+        compositor.composite(&full);
+        let row = raw_frame(
+            (0, 1, 3, 1),
+            DisposalMethod::DoNotDispose,
+            Some(0),
+            &[0, 2, 0],
+        );
+        assert_eq!(compositor.composite(&row), [1, 1, 1, 1, 2, 1]);
+    }
+
+    #[test]
+    fn clips_frames_going_past_screen() {
+        let mut compositor = indexed_compositor();
+        let frame = raw_frame(
+            (2, 1, 2, 2),
+            DisposalMethod::RestoreToPrevious,
+            None,
+            &[1, 2, 3, 3],
+        );
+        assert_eq!(compositor.composite(&frame), [0, 0, 0, 0, 0, 1]);
+        let outside = raw_frame((5, 5, 1, 1), DisposalMethod::DoNotDispose, None, &[2]);
+        assert_eq!(compositor.composite(&outside), [0; 6]);
+    }
+
+    #[test]
+    fn restores_to_transparent_when_keeping_alpha() {
+        let options = DecodeOptions {
+            transparency: TransparencyMode::KeepAlpha,
+            ..Default::default()
+        };
+        let mut compositor = Compositor::new(2, 1, PALETTE.get(3).copied(), &options);
+        assert_eq!(compositor.canvas(), [0; 8]);
+        let frame = raw_frame(
+            (0, 0, 1, 1),
+            DisposalMethod::RestoreToBackgroundColor,
+            None,
+            &[2],
+        );
+        assert_eq!(compositor.composite(&frame), [255, 0, 0, 255, 0, 0, 0, 0]);
+        let frame = raw_frame((1, 0, 1, 1), DisposalMethod::DoNotDispose, None, &[1]);
+        assert_eq!(
+            compositor.composite(&frame),
+            [0, 0, 0, 0, 255, 255, 255, 255]
+        );
+    }
+
+    #[test]
+    fn resamples_to_square_pixels() {
+        let mut gif = GifBuilder::new(2, 2, 0)
+            .image(0, 0, 2, 2, &[0, 1, 2, 3])
+            .build();
+        let options = DecodeOptions {
+            pixel_format: PixelFormat::Indexed,
+            square_pixels: true,
+            ..Default::default()
+        };
+        let decode = |gif: &[u8]| {
+            let mut decoder =
+                GifDecoder::with_options(SliceReader::new(gif), options.clone()).unwrap();
+            decoder.next_frame().unwrap().unwrap()
+        };
+
+        // Pixel aspect ratio of (113 + 15) / 64 = 2: pixels twice as wide
+        gif[12] = 113;
+        let frame = decode(&gif);
+        assert_eq!((frame.width, frame.height), (4, 2));
+        assert_eq!(frame.data, [0, 0, 1, 1, 2, 2, 3, 3]);
+
+        // Pixel aspect ratio of (17 + 15) / 64 = 0.5: pixels twice as tall
+        gif[12] = 17;
+        let frame = decode(&gif);
+        assert_eq!((frame.width, frame.height), (2, 4));
+        assert_eq!(frame.data, [0, 1, 0, 1, 2, 3, 2, 3]);
+
+        // Pixel aspect ratio of (81 + 15) / 64 = 1.5: columns are repeated
+        // every other one
+        let mut gif = GifBuilder::new(2, 1, 0).image(0, 0, 2, 1, &[1, 2]).build();
+        gif[12] = 81;
+        let frame = decode(&gif);
+        assert_eq!((frame.width, frame.height), (3, 1));
+        assert_eq!(frame.data, [1, 1, 2]);
+    }
+
+    #[test]
+    fn writes_pixels_in_each_format() {
+        let color = Rgb {
+            r: 0x12,
+            g: 0x34,
+            b: 0x56,
+        };
+        let background = Rgb {
+            r: 0x80,
+            g: 0x40,
+            b: 0x20,
+        };
+        // A pixel of `color`, then a transparent one
+        let mut frame = raw_frame((0, 0, 2, 1), DisposalMethod::DoNotDispose, Some(1), &[0, 1]);
+        frame.palette = vec![color, PALETTE[1]];
+        let expected: [(PixelFormat, &[u8]); 6] = [
+            (PixelFormat::Rgba8, &[0x12, 0x34, 0x56, 0xFF, 0, 0, 0, 0]),
+            (PixelFormat::Bgra8, &[0x56, 0x34, 0x12, 0xFF, 0, 0, 0, 0]),
+            (
+                PixelFormat::RgbaPremultiplied,
+                &[0x12, 0x34, 0x56, 0xFF, 0, 0, 0, 0],
+            ),
+            // No alpha channel: the background color is kept
+            (PixelFormat::Rgb8, &[0x12, 0x34, 0x56, 0x80, 0x40, 0x20]),
+            // 0b00010_001101_01010 then 0b10000_010000_00100
+            (PixelFormat::Rgb565, &[0xAA, 0x11, 0x04, 0x82]),
+            // Background color index of 3
+            (PixelFormat::Indexed, &[0, 3]),
+        ];
+        for (pixel_format, data) in &expected {
+            let options = DecodeOptions {
+                pixel_format: *pixel_format,
+                transparency: TransparencyMode::KeepAlpha,
+                ..Default::default()
+            };
+            let mut compositor =
+                Compositor::with_background_index(2, 1, Some(background), 3, None, &options);
+            let output = compositor.render_frame(frame.clone());
+            assert_eq!(output.data, *data, "{:?}", pixel_format);
+            match pixel_format {
+                PixelFormat::Indexed => assert_eq!(output.palette, Some(frame.palette.clone())),
+                _ => assert_eq!(output.palette, None),
+            }
+        }
+    }
+}
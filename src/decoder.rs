@@ -1,178 +1,289 @@
+/// Maximum size, in bits, a LZW code can have in a GIF buffer.
+const MAX_CODE_SIZE: u8 = 12;
+
+/// Maximum LZW minimum code size in a GIF buffer, as the values coded are
+/// color indices, which are bytes.
+const MAX_MIN_CODE_SIZE: u8 = 8;
+
+/// Maximum number of entries of the code table, one per possible code.
+const TABLE_SIZE: usize = 1 << MAX_CODE_SIZE;
+
+/// Errors which may be encountered while decoding LZW-compressed data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LzwError {
+    /// The initial code size given is out of the range supported by the GIF
+    /// format.
+    InvalidMinCodeSize(u8),
+
+    /// A code was encountered which corresponds to no value.
+    /// `offset` is the offset of the byte on which that code started (the
+    /// first byte containing some of its bits) in the whole compressed data
+    /// given to the `LzwDecoder` since its creation, as the code may have
+    /// started in the buffer given to a previous `decode_next` (or
+    /// `decode_into`) call.
+    InvalidCode { code: u16, offset: usize },
+}
+
+/// Outcome of a `LzwDecoder::decode_into` call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LzwProgress {
+    /// Number of bytes of the compressed data which were consumed.
+    /// The following ones should be given to the next `decode_into` call.
+    pub consumed: usize,
+
+    /// Number of decompressed bytes written at the start of the output slice.
+    pub written: usize,
+
+    /// Error which stopped decoding, if one. The bytes written until then are
+    /// still valid.
+    pub result: Result<(), LzwError>,
+}
+
 /// Decompress data compressed in the LZW compression format.
-/// This struct keeps state in between `decode_next` calls so that you can call
-/// it with contiguous subparts of the compressed data as you read them.
+/// This struct keeps state in between `decode_next` (or `decode_into`) calls
+/// so that you can call it with contiguous subparts of the compressed data as
+/// you read them.
+///
+/// Codes are stored in a table where each one is described by the code of its
+/// value without its last byte (its prefix) and that last byte (its suffix),
+/// so that adding a new code never allocates.
 pub struct LzwDecoder {
-    current_val: Vec<u8>,
+    /// The minimum code size at the instantiation of the `LzwDecoder`.
+    min_code_size: u8,
+
+    /// Current code size that should be read from a compressed buffer.
+    code_size: u8,
+
+    /// Code which will be given to the next value added to the table.
+    next_code: u16,
+
+    /// For each code, the code of its value without its last byte.
+    prefixes: Box<[u16; TABLE_SIZE]>,
+
+    /// For each code, the last byte of its value.
+    suffixes: Box<[u8; TABLE_SIZE]>,
+
+    /// For each code, the first byte of its value.
+    first_bytes: Box<[u8; TABLE_SIZE]>,
+
+    /// For each code, the length of its value.
+    /// `0` for the `clear` and `stop` codes.
+    lengths: Box<[u16; TABLE_SIZE]>,
+
+    /// Last code decoded since the table was last cleared, if one.
+    prev_code: Option<u16>,
+
+    /// Value which did not entirely fit in the output slice: its code and how
+    /// many of its bytes were already written.
+    pending: Option<(u16, usize)>,
+
     bit_reader: LsbReader,
-    dict: LzwDictionary,
+
+    /// Number of bytes of compressed data consumed since the creation of the
+    /// `LzwDecoder`.
+    nb_consumed: usize,
+
+    /// Set to `true` once the `stop` code has been encountered.
+    finished: bool,
 }
 
 impl LzwDecoder {
     /// Create a new LzwDecoder, with the given initial code size that should
     /// have been parsed from the GIF buffer before its compressed data.
-    pub fn new(min_code_size: u8) -> LzwDecoder {
-        LzwDecoder {
-            current_val: vec![],
+    pub fn new(min_code_size: u8) -> Result<LzwDecoder, LzwError> {
+        if min_code_size == 0 || min_code_size > MAX_MIN_CODE_SIZE {
+            return Err(LzwError::InvalidMinCodeSize(min_code_size));
+        }
+        let mut decoder = LzwDecoder {
+            min_code_size,
+            code_size: min_code_size + 1,
+            next_code: 0,
+            prefixes: Box::new([0; TABLE_SIZE]),
+            suffixes: Box::new([0; TABLE_SIZE]),
+            first_bytes: Box::new([0; TABLE_SIZE]),
+            lengths: Box::new([0; TABLE_SIZE]),
+            prev_code: None,
+            pending: None,
             bit_reader: LsbReader::new(),
-            dict: LzwDictionary::new(min_code_size),
+            nb_consumed: 0,
+            finished: false,
+        };
+        // Codes under the `clear` code are each a single byte, and are never
+        // replaced
+        for code in 0..decoder.clear_code() as usize {
+            decoder.suffixes[code] = code as u8;
+            decoder.first_bytes[code] = code as u8;
+            decoder.lengths[code] = 1;
         }
+        decoder.clear();
+        Ok(decoder)
+    }
+
+    /// Returns `true` once the `stop` code has been encountered, after which
+    /// any data given to `decode_next` is ignored.
+    pub fn is_finished(&self) -> bool {
+        self.finished
     }
 
     /// Decode the next block of compressed data.
-    pub fn decode_next(&mut self, buf: &[u8]) -> Vec<u8> {
+    ///
+    /// This allocates the returned `Vec`: `decode_into` should be preferred
+    /// when performance matters.
+    pub fn decode_next(&mut self, buf: &[u8]) -> Result<Vec<u8>, LzwError> {
         let mut decoded_buf: Vec<u8> = vec![];
-        let mut current_offset = 0;
+        let mut chunk = [0; 4096];
+        let mut offset = 0;
         loop {
-            let curr_code_size = self.dict.get_curr_code_size();
-            match self
-                .bit_reader
-                .get_next_code(&buf[current_offset..], curr_code_size)
-            {
-                (_, None) => {
-                    return decoded_buf;
-                }
-                (consumed, Some(code)) => {
-                    current_offset += consumed;
-                    match self.dict.get_value(code) {
-                        DictionaryValue::Clear => {
-                            self.dict.clear();
-                            self.current_val = vec![];
-                        }
-                        DictionaryValue::Stop => return decoded_buf,
-                        DictionaryValue::None => {
-                            eprintln!("Error: Impossible to decode, found unknown code: {}", code);
-                            std::process::exit(1);
-                        }
-                        DictionaryValue::Repeat => {
-                            if self.current_val.is_empty() {
-                                eprintln!(
-                                    "Error: Impossible to decode, found unknown code: {}",
-                                    code
-                                );
-                                std::process::exit(1);
-                            }
-                            let first_val = self.current_val[0];
-                            self.current_val.push(first_val);
-                            decoded_buf.extend(self.current_val.clone());
-                            self.dict.push_new_value(self.current_val.clone());
-                        }
-                        DictionaryValue::Value(val) => {
-                            self.current_val.push(val[0]);
-                            if self.current_val.len() != 1 {
-                                // Only at one at the beginning or when cleared
-                                let val_cloned = val.clone();
-                                self.dict.push_new_value(self.current_val.clone());
-                                self.current_val = val_cloned;
-                            }
-                            decoded_buf.extend(val);
-                        }
-                    }
-                }
+            let progress = self.decode_into(&buf[offset..], &mut chunk);
+            decoded_buf.extend_from_slice(&chunk[..progress.written]);
+            progress.result?;
+            offset += progress.consumed;
+            if progress.written < chunk.len() {
+                return Ok(decoded_buf);
             }
         }
     }
-}
-
-/// Store codes and related values for a LZW decoder.
-#[derive(Debug)]
-struct LzwDictionary {
-    /// The minimum code size at the instanciation of the LzwDictionary.
-    min_code_size: u8,
-
-    /// Current code size that should be read from a compressed buffer.
-    curr_code_size: u8,
 
-    /// Table of correspondance between codes and corresponding values.
-    /// Here a vec of Option type, where the code will be the index and the
-    /// value will be wrapped in a `Some(value)` form.
+    /// Decode the next block of compressed data directly into `out`.
     ///
-    /// The `None` form will be used for the two special codes `clear` and
-    /// `stop` as those are easy to calculate and would make the table take
-    /// more space than it should (an Option<Vec<T>> doesn't augment the memory
-    /// imprint of a Vec<T>).
-    table: Vec<Option<Vec<u8>>>,
-}
-
-/// Value returned when interrogating the dictionnary through its `get_value`
-/// method.
-#[derive(Clone, Debug)]
-enum DictionaryValue {
-    /// The code given was invalid, no related value was found.
-    None,
-
-    /// The code given corresponds to a `clear` code.
-    Clear,
+    /// Decoding stops once all of `buf` has been consumed, once `out` is full,
+    /// once the `stop` code is encountered or on error, whichever comes
+    /// first. When `out` gets full in the middle of a value, the rest of it is
+    /// written by the next call.
+    pub fn decode_into(&mut self, buf: &[u8], out: &mut [u8]) -> LzwProgress {
+        let mut consumed = 0;
+        let mut written = 0;
+        if let Some((code, start)) = self.pending.take() {
+            written = self.write_value(code, start, out);
+            if start + written < self.lengths[code as usize] as usize {
+                self.pending = Some((code, start + written));
+            }
+        }
+        while !self.finished && written < out.len() {
+            let (nb_read, code) = self
+                .bit_reader
+                .get_next_code(&buf[consumed..], self.code_size);
+            consumed += nb_read;
+            let code = match code {
+                Some(code) => code,
+                None => break,
+            };
+            if code == self.clear_code() {
+                self.clear();
+                continue;
+            }
+            if code == self.clear_code() + 1 {
+                // `stop` code
+                self.finished = true;
+                break;
+            }
+            let prev_code = match self.prev_code {
+                Some(prev_code) => prev_code,
+                None => {
+                    // Only single bytes are known right after a clear
+                    if code >= self.next_code {
+                        return self.invalid_code(code, consumed, written);
+                    }
+                    out[written] = code as u8;
+                    written += 1;
+                    self.prev_code = Some(code);
+                    continue;
+                }
+            };
 
-    /// The code given corresponds to a `stop` code.
-    Stop,
+            // The new value is the previous one followed by the first byte of
+            // the current one, which is itself when the code is not yet known
+            let first_byte = if code < self.next_code {
+                self.first_bytes[code as usize]
+            } else if code == self.next_code {
+                self.first_bytes[prev_code as usize]
+            } else {
+                return self.invalid_code(code, consumed, written);
+            };
+            self.add_code(prev_code, first_byte);
 
-    /// The code given is for the special `repeat` case, where you have to add
-    /// to your current value the first value decoded.
-    Repeat,
+            let nb_written = self.write_value(code, 0, &mut out[written..]);
+            if nb_written < self.lengths[code as usize] as usize {
+                self.pending = Some((code, nb_written));
+            }
+            written += nb_written;
+            self.prev_code = Some(code);
+        }
+        self.nb_consumed += consumed;
+        LzwProgress {
+            consumed,
+            written,
+            result: Ok(()),
+        }
+    }
 
-    /// The code given was linked to a found value.
-    Value(Vec<u8>),
-}
+    /// Returns the `LzwProgress` of a `decode_into` call stopped by the
+    /// invalid code `code`, which was just read, after `consumed` bytes were
+    /// consumed.
+    fn invalid_code(&mut self, code: u16, consumed: usize, written: usize) -> LzwProgress {
+        self.nb_consumed += consumed;
+        // Bits of the data consumed until now, minus those not yet read and
+        // those of the code itself
+        let nb_bits = self.nb_consumed * 8 - self.bit_reader.bits as usize;
+        let first_bit = nb_bits - self.code_size as usize;
+        LzwProgress {
+            consumed,
+            written,
+            result: Err(LzwError::InvalidCode {
+                code,
+                offset: first_bit / 8,
+            }),
+        }
+    }
 
-impl LzwDictionary {
-    /// Create a new LzwDictionary with the given initial code size.
-    fn new(min_code_size: u8) -> LzwDictionary {
-        let table: Vec<Option<Vec<u8>>> = Vec::with_capacity(512);
-        let mut dict = LzwDictionary {
-            min_code_size,
-            curr_code_size: min_code_size + 1,
-            table,
-        };
-        dict.clear();
-        dict
+    /// Code resetting the table to its initial state.
+    fn clear_code(&self) -> u16 {
+        1 << self.min_code_size
     }
 
-    /// Reset the LzwDictionary to its initial state.
+    /// Reset the table to its initial state.
     /// To call when a `clear` code is encountered.
     fn clear(&mut self) {
-        self.table.clear();
-        self.curr_code_size = self.min_code_size + 1;
-        let initial_table_size: u16 = 1 << self.min_code_size as u16;
-        for i in 0..initial_table_size {
-            self.table.push(Some(vec![i as u8]));
-        }
-        self.table.push(None); // `clear` code
-        self.table.push(None); // `code` size
+        self.code_size = self.min_code_size + 1;
+        // Codes after the `clear` and `stop` ones
+        self.next_code = self.clear_code() + 2;
+        self.prev_code = None;
     }
 
-    /// Get the value corresponding to the code given.
-    fn get_value(&self, code: u16) -> DictionaryValue {
-        use std::cmp::Ordering;
-
-        let code = code as usize;
-        match code.cmp(&self.table.len()) {
-            Ordering::Less => match &self.table[code] {
-                Some(val) => DictionaryValue::Value(val.clone()),
-                None => {
-                    if code == 1 << self.min_code_size as u16 {
-                        DictionaryValue::Clear
-                    } else {
-                        DictionaryValue::Stop
-                    }
-                }
-            },
-            Ordering::Equal => DictionaryValue::Repeat,
-            Ordering::Greater => DictionaryValue::None,
+    /// Add to the table the value of `prefix` followed by `suffix`, at the
+    /// next code, if the table is not full.
+    fn add_code(&mut self, prefix: u16, suffix: u8) {
+        let code = self.next_code as usize;
+        if code == TABLE_SIZE {
+            return;
         }
-    }
-
-    /// Add a new value at the next code.
-    fn push_new_value(&mut self, val: Vec<u8>) {
-        self.table.push(Some(val));
-        if self.table.len() == (1 << self.curr_code_size as usize) && self.curr_code_size < 12 {
-            self.curr_code_size += 1;
+        let prefix = prefix as usize;
+        self.prefixes[code] = prefix as u16;
+        self.suffixes[code] = suffix;
+        self.first_bytes[code] = self.first_bytes[prefix];
+        self.lengths[code] = self.lengths[prefix] + 1;
+        self.next_code += 1;
+        if self.next_code as usize == 1 << self.code_size && self.code_size < MAX_CODE_SIZE {
+            self.code_size += 1;
         }
     }
 
-    /// Returns the current code size you have to read from the compressed
-    /// buffer.
-    fn get_curr_code_size(&self) -> u8 {
-        self.curr_code_size
+    /// Write to `out` as much of the value of `code` as fits in it, starting
+    /// from its byte at the index `start`.
+    /// Returns the number of bytes written.
+    fn write_value(&self, code: u16, start: usize, out: &mut [u8]) -> usize {
+        let length = self.lengths[code as usize] as usize;
+        let nb_bytes = (length - start).min(out.len());
+        // Values are described from their end: skip the bytes not written
+        let mut code = code as usize;
+        for _ in start + nb_bytes..length {
+            code = self.prefixes[code] as usize;
+        }
+        for byte in out[..nb_bytes].iter_mut().rev() {
+            *byte = self.suffixes[code];
+            code = self.prefixes[code] as usize;
+        }
+        nb_bytes
     }
 }
 
@@ -198,6 +309,7 @@ impl LsbReader {
     /// Returns both the number or bytes read from the buffer and the read u16
     /// value.
     /// Warning: `code_size` cannot be superior to 16.
+    #[inline]
     fn get_next_code(&mut self, mut buf: &[u8], code_size: u8) -> (usize, Option<u16>) {
         if code_size > 16 {
             // This is a logic error the program should have prevented this
@@ -229,3 +341,165 @@ impl LsbReader {
         (consumed, Some(res as u16))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pack `(code, code_size)` pairs into a byte buffer, least significant
+    /// bit first, as they are in a GIF buffer.
+    fn pack(codes: &[(u16, u8)]) -> Vec<u8> {
+        let mut buf = vec![];
+        let mut acc: u32 = 0;
+        let mut bits = 0;
+        for &(code, code_size) in codes {
+            acc |= (code as u32) << bits;
+            bits += code_size;
+            while bits >= 8 {
+                buf.push(acc as u8);
+                acc >>= 8;
+                bits -= 8;
+            }
+        }
+        if bits > 0 {
+            buf.push(acc as u8);
+        }
+        buf
+    }
+
+    #[test]
+    fn rejects_invalid_min_code_sizes() {
+        assert!(matches!(
+            LzwDecoder::new(0),
+            Err(LzwError::InvalidMinCodeSize(0))
+        ));
+        assert!(matches!(
+            LzwDecoder::new(9),
+            Err(LzwError::InvalidMinCodeSize(9))
+        ));
+        assert!(LzwDecoder::new(1).is_ok());
+        assert!(LzwDecoder::new(8).is_ok());
+    }
+
+    #[test]
+    fn decodes_code_not_yet_in_table() {
+        // `6` is the code about to be added: the previous value followed by
+        // its own first byte
+        let data = pack(&[(4, 3), (1, 3), (6, 3), (6, 3), (5, 3)]);
+        let mut decoder = LzwDecoder::new(2).unwrap();
+        assert_eq!(decoder.decode_next(&data).unwrap(), [1, 1, 1, 1, 1]);
+        assert!(decoder.is_finished());
+    }
+
+    #[test]
+    fn handles_clear_and_stop_codes() {
+        // The table is reset by the second `clear` code, so `6` is `0 0`
+        // again instead of `1 2`
+        let codes = [
+            (4, 3),
+            (1, 3),
+            (2, 3),
+            (4, 3),
+            (0, 3),
+            (6, 3),
+            (5, 3),
+            (3, 3),
+        ];
+        let data = pack(&codes);
+        let mut decoder = LzwDecoder::new(2).unwrap();
+        assert_eq!(decoder.decode_next(&data).unwrap(), [1, 2, 0, 0, 0]);
+        assert!(decoder.is_finished());
+        // Data after the `stop` code is ignored
+        assert!(decoder.decode_next(&[0xFF; 4]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn grows_code_size_until_table_is_full() {
+        // Each literal after the first adds a code, the code size growing when
+        // the next code does not fit in it anymore, up to 12 bits
+        let mut codes = vec![(4, 3)];
+        let mut code_size = 3;
+        let mut next_code = 6;
+        for i in 0..5000 {
+            codes.push(((i % 4) as u16, code_size));
+            if i > 0 && next_code < 4096 {
+                next_code += 1;
+                if next_code == 1 << code_size && code_size < 12 {
+                    code_size += 1;
+                }
+            }
+        }
+        assert_eq!(code_size, 12);
+        // Clearing goes back to the initial code size
+        codes.extend_from_slice(&[(4, 12), (3, 3), (5, 3)]);
+
+        let mut expected: Vec<u8> = (0..5000).map(|i| (i % 4) as u8).collect();
+        expected.push(3);
+        let mut decoder = LzwDecoder::new(2).unwrap();
+        assert_eq!(decoder.decode_next(&pack(&codes)).unwrap(), expected);
+        assert!(decoder.is_finished());
+    }
+
+    #[test]
+    fn resumes_values_across_calls() {
+        let data = pack(&[(4, 3), (1, 3), (6, 3), (7, 3), (2, 4), (5, 4)]);
+        let expected = LzwDecoder::new(2).unwrap().decode_next(&data).unwrap();
+        assert_eq!(expected, [1, 1, 1, 1, 1, 1, 2]);
+
+        // One byte of output and of input at a time
+        let mut decoder = LzwDecoder::new(2).unwrap();
+        let mut decoded = vec![];
+        let mut offset = 0;
+        while !decoder.is_finished() {
+            let mut out = [0; 1];
+            let end = (offset + 1).min(data.len());
+            let progress = decoder.decode_into(&data[offset..end], &mut out);
+            assert_eq!(progress.result, Ok(()));
+            decoded.extend_from_slice(&out[..progress.written]);
+            offset += progress.consumed;
+        }
+        assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    fn reports_invalid_code_offset() {
+        // `7` is in the first byte, right after a `clear` code
+        let data = pack(&[(4, 3), (7, 3)]);
+        let mut decoder = LzwDecoder::new(2).unwrap();
+        assert_eq!(
+            decoder.decode_next(&data),
+            Err(LzwError::InvalidCode { code: 7, offset: 0 })
+        );
+
+        // `7`, being more than one code ahead, starts in the first byte and
+        // ends in the second one
+        let data = pack(&[(4, 3), (1, 3), (7, 3)]);
+        let mut decoder = LzwDecoder::new(2).unwrap();
+        let mut out = [0; 16];
+        let progress = decoder.decode_into(&data, &mut out);
+        assert_eq!(progress.written, 1);
+        assert_eq!(progress.consumed, 2);
+        assert_eq!(
+            progress.result,
+            Err(LzwError::InvalidCode { code: 7, offset: 0 })
+        );
+
+        // Same when both bytes are given through separate calls
+        let mut decoder = LzwDecoder::new(2).unwrap();
+        assert_eq!(decoder.decode_into(&data[..1], &mut out).written, 1);
+        let progress = decoder.decode_into(&data[1..], &mut out);
+        assert_eq!(
+            progress.result,
+            Err(LzwError::InvalidCode { code: 7, offset: 0 })
+        );
+
+        // `7` starts in the third byte
+        let data = pack(&[(4, 3), (1, 3), (4, 3), (2, 3), (4, 3), (3, 3), (7, 3)]);
+        let mut decoder = LzwDecoder::new(2).unwrap();
+        assert_eq!(decoder.decode_next(&data[..2]), Ok(vec![1, 2]));
+        assert_eq!(
+            decoder.decode_next(&data[2..]),
+            Err(LzwError::InvalidCode { code: 7, offset: 2 })
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use crate::limits::ResourceLimit;
 use std::{error, fmt};
 
 /// Result type returned by the GIF parsing and decoding logic
@@ -42,13 +43,37 @@ pub enum GifParsingError {
 
     /// An unknown type of "extension block" was encountered.
     /// As we don't know anything about the size of the data it brings with it,
-    /// we prefer aborting there.
+    /// we prefer aborting there (unless decoding leniently, in which case it is
+    /// skipped like any other extension).
     UnrecognizedExtension(u8),
 
     /// An unknown type of block was encountered.
     /// As we don't know anything about the size of the data it brings with it,
-    /// we prefer aborting there.
+    /// we prefer aborting there (unless decoding leniently, in which case bytes
+    /// are skipped until the next known block).
     UnrecognizedBlock { code: u8, position: usize },
+
+    /// A frame was expected at `position`, where it was when the `FrameIndex`
+    /// was built, but another block was found instead. This means that the
+    /// GIF buffer changed since then.
+    UnexpectedBlock { position: usize },
+
+    /// The LZW minimum code size announced before an image's data is out of
+    /// the range supported by the GIF format.
+    InvalidLzwCodeSize { code_size: u8, position: usize },
+
+    /// The LZW-compressed image data contained a code which corresponds to no
+    /// value. `position` is the position in the GIF buffer of the byte on which
+    /// that code started.
+    InvalidLzwCode { code: u16, position: usize },
+
+    /// The receiving side of the channel on which decoding events are sent has
+    /// been dropped.
+    EventChannelClosed,
+
+    /// Decoding would exceed one of the limits set through
+    /// `DecodeOptions::limits`.
+    LimitExceeded(ResourceLimit),
 }
 
 impl From<std::io::Error> for GifParsingError {
@@ -70,6 +95,11 @@ impl error::Error for GifParsingError {
             GifParsingError::NoColorTable => None,
             GifParsingError::UnrecognizedExtension(_) => None,
             GifParsingError::UnrecognizedBlock { .. } => None,
+            GifParsingError::UnexpectedBlock { .. } => None,
+            GifParsingError::InvalidLzwCodeSize { .. } => None,
+            GifParsingError::InvalidLzwCode { .. } => None,
+            GifParsingError::EventChannelClosed => None,
+            GifParsingError::LimitExceeded(_) => None,
         }
     }
 }
@@ -127,6 +157,38 @@ impl fmt::Display for GifParsingError {
                 "Unrecognized block with code {} at position {}.",
                 code, position
             ),
+
+            GifParsingError::UnexpectedBlock { position } => write!(
+                f,
+                "Expected a frame at position {}, found another block.",
+                position
+            ),
+
+            GifParsingError::InvalidLzwCodeSize {
+                code_size,
+                position,
+            } => write!(
+                f,
+                "Invalid LZW minimum code size {} at position {}.",
+                code_size, position
+            ),
+
+            GifParsingError::InvalidLzwCode { code, position } => write!(
+                f,
+                "Invalid LZW code {} found at position {}.",
+                code, position
+            ),
+
+            GifParsingError::EventChannelClosed => {
+                write!(
+                    f,
+                    "Impossible to communicate decoding events: channel closed."
+                )
+            }
+
+            GifParsingError::LimitExceeded(limit) => {
+                write!(f, "Resource limit exceeded: {}.", limit)
+            }
         }
     }
 }
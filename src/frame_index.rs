@@ -0,0 +1,135 @@
+use crate::error::Result;
+use crate::gif_reader::GifRead;
+use crate::parser::{DisposalMethod, FrameInfo, FrameParser, GifHeader, SkippedFrame};
+
+/// Index of every frame in a GIF buffer, built from its block structure alone
+/// (no image data is decoded), allowing to directly reach any frame.
+#[derive(Debug, Clone)]
+pub struct FrameIndex {
+    /// Information on each frame, in order.
+    entries: Vec<FrameIndexEntry>,
+
+    /// Looping information found in the GIF buffer, if one.
+    loop_count: Option<u16>,
+}
+
+/// Information on a single frame of a `FrameIndex`.
+#[derive(Debug, Clone)]
+pub struct FrameIndexEntry {
+    /// Information on the frame: position in the GIF buffer, rectangle,
+    /// disposal method...
+    pub info: FrameInfo,
+
+    /// Time, in hundredths (1/100) of a second, during which that frame should
+    /// be displayed.
+    /// `None` if no delay was specified for that frame.
+    pub delay: Option<u16>,
+
+    /// Index of the first frame needed to render that frame: no frame before
+    /// it has any influence on the rendered canvas.
+    /// Equal to the frame's own index if it does not depend on any previous
+    /// frame.
+    pub chain_start: usize,
+}
+
+impl FrameIndex {
+    /// Build the `FrameIndex` by going through the GIF buffer, whose reader
+    /// should be positioned just after the header given, until its Trailer.
+    pub fn scan(rdr: &mut impl GifRead, header: &GifHeader) -> Result<FrameIndex> {
+        FrameIndex::scan_with(rdr, header, &mut FrameParser::new())
+    }
+
+    /// Build the `FrameIndex` like `scan` does, with the given `FrameParser`
+    /// - which can then be used to obtain the other information encountered.
+    pub(crate) fn scan_with(
+        rdr: &mut impl GifRead,
+        header: &GifHeader,
+        frame_parser: &mut FrameParser,
+    ) -> Result<FrameIndex> {
+        let mut entries: Vec<FrameIndexEntry> = vec![];
+
+        // First frame on which the canvas depends before rendering the next
+        // frame.
+        let mut next_chain_start = 0;
+        while let Some(SkippedFrame { info, delay, .. }) = frame_parser.skip_frame(rdr)? {
+            let idx = entries.len();
+            let covers_screen = info.left == 0
+                && info.top == 0
+                && info.width >= header.width
+                && info.height >= header.height;
+
+            // A frame covering the whole screen without transparency hides
+            // every previous one.
+            let chain_start = if covers_screen && info.transparent_color_index.is_none() {
+                idx
+            } else {
+                next_chain_start
+            };
+            next_chain_start = match info.disposal_method {
+                DisposalMethod::RestoreToBackgroundColor if covers_screen => idx + 1,
+                DisposalMethod::RestoreToPrevious => next_chain_start,
+                _ => chain_start,
+            };
+            entries.push(FrameIndexEntry {
+                info,
+                delay,
+                chain_start,
+            });
+        }
+        Ok(FrameIndex {
+            entries,
+            loop_count: frame_parser.loop_count(),
+        })
+    }
+
+    /// Number of frames in the GIF buffer.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the GIF buffer contains no frame.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the information on the frame at the index `n`, if one.
+    pub fn get(&self, n: usize) -> Option<&FrameIndexEntry> {
+        self.entries.get(n)
+    }
+
+    /// Returns the information on every frame, in order.
+    pub fn entries(&self) -> &[FrameIndexEntry] {
+        &self.entries
+    }
+
+    /// Looping information found in the GIF buffer. See
+    /// `GifDecoder::loop_count`.
+    pub fn loop_count(&self) -> Option<u16> {
+        self.loop_count
+    }
+
+    /// Returns the indexes, in order, of the frames which have to be rendered
+    /// before the frame at the index `n` for it to be correctly rendered.
+    ///
+    /// Frames whose disposal method is `RestoreToPrevious` are never needed, as
+    /// they leave the canvas as it was before them.
+    pub fn dependencies(&self, n: usize) -> Vec<usize> {
+        let entry = match self.entries.get(n) {
+            Some(entry) => entry,
+            None => return vec![],
+        };
+        (entry.chain_start..n)
+            .filter(|i| self.entries[*i].info.disposal_method != DisposalMethod::RestoreToPrevious)
+            .collect()
+    }
+
+    /// Returns the number of pixels of all frames preceding the frame at the
+    /// index `n`, which decoding them would have counted toward
+    /// `Limits::max_decoded_bytes`.
+    pub(crate) fn nb_pixels_before(&self, n: usize) -> u64 {
+        self.entries[..n.min(self.entries.len())]
+            .iter()
+            .map(|entry| entry.info.width as u64 * entry.info.height as u64)
+            .sum()
+    }
+}
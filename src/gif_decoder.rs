@@ -0,0 +1,836 @@
+use crate::application_extension::ApplicationExtension;
+use crate::compositor::Compositor;
+use crate::error::{GifParsingError, Result};
+use crate::frame_index::{FrameIndex, FrameIndexEntry};
+use crate::gif_reader::{GifRead, GifSeek, SliceReader};
+use crate::options::DecodeOptions;
+use crate::parallel;
+use crate::parser::{
+    self, Comment, Frame, FrameParser, FrameParts, GifEvent, GifHeader, NextFrame, RawFrame,
+};
+use crate::warning::Warning;
+
+/// Decode a GIF buffer into its succession of frames.
+///
+/// The GIF header (and its Global Color Table, if one) is parsed as soon as
+/// the `GifDecoder` is created so that the dimensions of the image can be
+/// known before any frame is decoded.
+///
+/// Frames are then decoded lazily, one at a time, either through the
+/// `next_frame` method or by using the `GifDecoder` as an `Iterator`.
+///
+/// Frames can also be obtained as they are stored in the GIF buffer, without
+/// any compositing, through the `next_raw_frame` method.
+///
+/// When the reader is seekable, any frame can also be directly reached through
+/// `seek_to_frame`.
+pub struct GifDecoder<R: GifRead> {
+    /// Reader returning the GIF buffer, just after the last parsed block.
+    rdr: R,
+    /// Header parsed at the beginning of the GIF buffer.
+    header: GifHeader,
+    /// Position in the GIF buffer of the first block after the header.
+    header_end: usize,
+    /// State kept in between decoded frames.
+    frame_parser: FrameParser,
+    /// Renders frames over the previous ones.
+    compositor: Compositor,
+    /// Index of the frames in the GIF buffer, once built.
+    frame_index: Option<FrameIndex>,
+    /// Set to `true` once an error has been returned, after which no frame is
+    /// decoded anymore.
+    failed: bool,
+    /// Number of threads decompressing image data in `decode`. See
+    /// `DecodeOptions::threads`.
+    nb_threads: usize,
+}
+
+impl<R: GifRead> GifDecoder<R> {
+    /// Create a new `GifDecoder` by parsing the header of the given GIF
+    /// buffer, with the default options.
+    pub fn new(rdr: R) -> Result<GifDecoder<R>> {
+        GifDecoder::with_options(rdr, DecodeOptions::default())
+    }
+
+    /// Create a new `GifDecoder` by parsing the header of the given GIF
+    /// buffer, with the given options.
+    pub fn with_options(mut rdr: R, options: DecodeOptions) -> Result<GifDecoder<R>> {
+        let header = parser::parse_header(&mut rdr)?;
+        GifDecoder::from_parts(rdr, header, options)
+    }
+
+    /// Create a new `GifDecoder` from a reader positioned just after the GIF
+    /// header, which has already been parsed.
+    /// Fails if the logical screen it declares exceeds the limits set in
+    /// `options`.
+    pub fn from_parts(rdr: R, header: GifHeader, options: DecodeOptions) -> Result<GifDecoder<R>> {
+        options
+            .limits
+            .check_screen(&header, options.square_pixels)?;
+        let compositor = Compositor::from_header(&header, &options);
+        let mut frame_parser = FrameParser::new();
+        frame_parser.set_apply_color_profile(options.apply_color_profile);
+        frame_parser.set_lenient(options.lenient);
+        frame_parser.set_strict(options.strict);
+        frame_parser.set_limits(options.limits);
+        frame_parser.check_header(&header);
+        Ok(GifDecoder {
+            header_end: rdr.get_pos(),
+            rdr,
+            header,
+            frame_parser,
+            compositor,
+            frame_index: None,
+            failed: false,
+            nb_threads: options.threads,
+        })
+    }
+
+    /// Returns the header parsed at the beginning of the GIF buffer.
+    pub fn header(&self) -> &GifHeader {
+        &self.header
+    }
+
+    /// Width of the GIF's logical screen, in pixels.
+    pub fn width(&self) -> u16 {
+        self.header.width
+    }
+
+    /// Height of the GIF's logical screen, in pixels.
+    pub fn height(&self) -> u16 {
+        self.header.height
+    }
+
+    /// Position in the GIF buffer just after the last parsed block, or where
+    /// parsing stopped if an error was returned.
+    pub fn position(&self) -> usize {
+        self.rdr.get_pos()
+    }
+
+    /// Looping information encountered until now.
+    /// `None` if no looping information has been found (yet), `Some(0)` if the
+    /// GIF should loop infinitely and any other value is the number of time it
+    /// should loop.
+    ///
+    /// Looping information generally comes before the first frame, but may in
+    /// theory be encountered anywhere in the GIF buffer.
+    pub fn loop_count(&self) -> Option<u16> {
+        self.frame_parser.loop_count()
+    }
+
+    /// Comments encountered until now, in order.
+    pub fn comments(&self) -> &[Comment] {
+        self.frame_parser.comments()
+    }
+
+    /// Warnings encountered until now, in order.
+    /// Some of them are only reported when decoding leniently (see
+    /// `DecodeOptions::lenient`), as they are errors otherwise.
+    pub fn warnings(&self) -> &[Warning] {
+        self.frame_parser.warnings()
+    }
+
+    /// XMP packet encountered until now (in an `"XMP DataXMP"` Application
+    /// Extension), if one.
+    pub fn xmp_packet(&self) -> Option<&[u8]> {
+        self.frame_parser.xmp_packet()
+    }
+
+    /// ICC color profile encountered until now (in an `"ICCRGBG1012"`
+    /// Application Extension), if one.
+    pub fn icc_profile(&self) -> Option<&[u8]> {
+        self.frame_parser.icc_profile()
+    }
+
+    /// Size, in bytes, of the buffer the GIF asks to fill before starting
+    /// playback, as indicated by the buffering sub-block of a NETSCAPE2.0
+    /// Application Extension, if one was encountered until now.
+    pub fn buffer_size(&self) -> Option<u32> {
+        self.frame_parser.buffer_size()
+    }
+
+    /// Register a function which will be called with every Application
+    /// Extension whose identifier, followed by its authentication code, is
+    /// `identifier` (e.g. `"NETSCAPE2.0"`), as they are encountered.
+    ///
+    /// This allows to get the data of Application Extensions this crate does
+    /// not know about. Several handlers can be registered for the same
+    /// identifier.
+    pub fn register_application_handler<F>(&mut self, identifier: &str, handler: F)
+    where
+        F: FnMut(&ApplicationExtension) + Send + 'static,
+    {
+        self.frame_parser
+            .register_application_handler(identifier, Box::new(handler));
+    }
+
+    /// Decode the next frame of the GIF buffer.
+    /// Returns `None` once all frames have been decoded or if a previous call
+    /// returned an error.
+    pub fn next_frame(&mut self) -> Result<Option<Frame>> {
+        match self.draw_next_frame()? {
+            Some((info, delay, palette)) => {
+                Ok(Some(self.compositor.output_frame(info, delay, palette)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Decode the next frame of the GIF buffer like `next_frame`, but into
+    /// `frame`, which has to be the last frame returned by this decoder (by
+    /// `next_frame`, `next_frame_into` or `seek_to_frame`), unmodified.
+    ///
+    /// Instead of copying the whole canvas in a new buffer, the buffer of
+    /// `frame` is reused and only the part of the canvas changed by the new
+    /// frame (and by the disposal of the previous one) is copied to it.
+    /// Returns `false`, leaving `frame` untouched, once all frames have been
+    /// decoded or if a previous call returned an error.
+    pub fn next_frame_into(&mut self, frame: &mut Frame) -> Result<bool> {
+        match self.draw_next_frame()? {
+            Some((info, delay, palette)) => {
+                self.compositor.update_frame(frame, info, delay, palette);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Decode the next frame of the GIF buffer straight onto the canvas of
+    /// the compositor, and return its information, delay and color table.
+    /// Returns `None` once all frames have been decoded or if a previous call
+    /// returned an error.
+    fn draw_next_frame(&mut self) -> Result<Option<FrameParts>> {
+        if self.failed {
+            return Ok(None);
+        }
+        self.frame_parser.resume_timer();
+        let res = self.try_draw_next_frame();
+        self.frame_parser.pause_timer();
+        if res.is_err() {
+            self.failed = true;
+        }
+        res
+    }
+
+    /// See `draw_next_frame`, without the handling of failures.
+    fn try_draw_next_frame(&mut self) -> Result<Option<FrameParts>> {
+        let next_frame = self
+            .frame_parser
+            .start_next_frame(&mut self.rdr, &self.header)?;
+        self.update_background_color();
+        match next_frame {
+            Some(NextFrame::Image(mut frame)) => {
+                self.compositor.begin_frame(frame.info(), frame.palette());
+                self.frame_parser.decode_image_data(
+                    &mut self.rdr,
+                    &mut frame,
+                    &mut self.compositor,
+                )?;
+                self.frame_parser
+                    .finish_frame(&mut frame, &mut self.compositor);
+                Ok(Some(frame.into_parts()))
+            }
+            Some(NextFrame::PlainText(raw_frame)) => {
+                self.compositor.composite(&raw_frame);
+                Ok(Some((raw_frame.info, raw_frame.delay, raw_frame.palette)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Convert the background color to sRGB, if colors have to be converted
+    /// from an ICC color profile.
+    /// That profile is only known once the blocks preceding the first frame
+    /// have been parsed.
+    fn update_background_color(&mut self) {
+        if let Some(transform) = self.frame_parser.color_transform() {
+            let background_color = self.header.background_color().map(|c| transform.convert(c));
+            self.compositor.set_background_color(background_color);
+        }
+    }
+
+    /// Decode the next frame of the GIF buffer, without compositing it over
+    /// the previous ones.
+    /// Returns `None` once all frames have been decoded or if a previous call
+    /// returned an error.
+    ///
+    /// Frames returned by this method are not taken into account when
+    /// compositing frames returned by `next_frame` afterwards, so you
+    /// generally want to only call one of them on a given `GifDecoder`.
+    pub fn next_raw_frame(&mut self) -> Result<Option<RawFrame>> {
+        if self.failed {
+            return Ok(None);
+        }
+        self.frame_parser.resume_timer();
+        let res = self
+            .frame_parser
+            .next_raw_frame(&mut self.rdr, &self.header);
+        self.frame_parser.pause_timer();
+        if res.is_err() {
+            self.failed = true;
+        }
+        res
+    }
+
+    /// Convert this `GifDecoder` into an `Iterator` over its remaining frames,
+    /// without compositing them.
+    pub fn into_raw_frames(self) -> RawFrames<R> {
+        RawFrames { decoder: self }
+    }
+
+    /// Decode every remaining frame of the GIF buffer and communicate them,
+    /// as well as other information on the GIF, through the given `Sender`.
+    ///
+    /// If `DecodeOptions::threads` is set, image data is decompressed on that
+    /// many threads, the events sent being the same.
+    pub fn decode(mut self, tx: std::sync::mpsc::Sender<GifEvent>) -> Result<()> {
+        let mut events = EventSender::new(tx);
+        if self.nb_threads > 0 {
+            self.frame_parser.resume_timer();
+            return parallel::decode(
+                &mut self.rdr,
+                &self.header,
+                &mut self.frame_parser,
+                &mut self.compositor,
+                self.nb_threads,
+                &mut events,
+            );
+        }
+        let mut nb_sent_comments = 0;
+        let mut nb_sent_warnings = 0;
+        while let Some(frame) = self.next_frame()? {
+            // Comments and warnings all came before that frame
+            let comments = &self.comments()[nb_sent_comments..];
+            let warnings = &self.warnings()[nb_sent_warnings..];
+            events.send_frame(self.loop_count(), comments, warnings, frame)?;
+            nb_sent_comments = self.comments().len();
+            nb_sent_warnings = self.warnings().len();
+        }
+        let comments = &self.comments()[nb_sent_comments..];
+        let warnings = &self.warnings()[nb_sent_warnings..];
+        events.send_end(self.loop_count(), comments, warnings)
+    }
+}
+
+impl<R: GifSeek> GifDecoder<R> {
+    /// Returns the index of every frame in the GIF buffer, building it first
+    /// if not already done.
+    ///
+    /// Building it means going through the whole GIF buffer once, without
+    /// decoding any image data. The position of the `GifDecoder` is left
+    /// unchanged.
+    pub fn frame_index(&mut self) -> Result<&FrameIndex> {
+        if self.frame_index.is_none() {
+            let initial_pos = self.rdr.get_pos();
+            self.rdr.seek_to(self.header_end)?;
+            self.frame_parser.resume_timer();
+            let mut scan_parser = self.frame_parser.derived_parser();
+            let index = FrameIndex::scan_with(&mut self.rdr, &self.header, &mut scan_parser);
+            self.frame_parser.pause_timer();
+            self.rdr.seek_to(initial_pos)?;
+            self.frame_index = Some(index?);
+
+            // Seeking skips the blocks before each frame, where the color
+            // profile is found
+            if let Some(profile) = scan_parser.icc_profile() {
+                self.frame_parser.set_icc_profile_if_none(profile);
+            }
+        }
+        Ok(self.frame_index.as_ref().unwrap())
+    }
+
+    /// Decode and return the frame at the index `n` (`0` being the first
+    /// frame), composited over the previous ones.
+    /// Returns `None` if there is no frame at that index.
+    ///
+    /// Only the frames needed to render it are decoded (see
+    /// `FrameIndex::dependencies`). Subsequent calls to `next_frame` will then
+    /// return the frames following it.
+    ///
+    /// Resource limits (see `DecodeOptions::limits`) are counted as if every
+    /// frame preceding it had been decoded, whichever frames were decoded
+    /// before: seeking again does not add to `Limits::max_decoded_bytes`.
+    /// Note that building the frame index already checks them for the whole
+    /// GIF buffer.
+    pub fn seek_to_frame(&mut self, n: usize) -> Result<Option<Frame>> {
+        let index = self.frame_index()?;
+        let entry = match index.get(n) {
+            Some(entry) => entry.clone(),
+            None => return Ok(None),
+        };
+        let dependencies: Vec<_> = index
+            .dependencies(n)
+            .into_iter()
+            .map(|i| (i, index.entries()[i].clone(), index.nb_pixels_before(i)))
+            .collect();
+        let nb_pixels_before = index.nb_pixels_before(n);
+
+        self.failed = false;
+        self.compositor.reset();
+        self.update_background_color();
+        for (idx, dependency, nb_pixels_before) in dependencies {
+            self.draw_frame_at(idx, &dependency, nb_pixels_before)?;
+        }
+        let (info, delay, palette) = self.draw_frame_at(n, &entry, nb_pixels_before)?;
+        Ok(Some(self.compositor.output_frame(info, delay, palette)))
+    }
+
+    /// Decode the frame at the index `frame_idx`, described by `entry` and
+    /// preceded by frames of `nb_pixels_before` pixels in total, by seeking
+    /// directly to it, and draw it on the canvas of the compositor.
+    /// Returns its information, delay and color table.
+    fn draw_frame_at(
+        &mut self,
+        frame_idx: usize,
+        entry: &FrameIndexEntry,
+        nb_pixels_before: u64,
+    ) -> Result<FrameParts> {
+        let info = &entry.info;
+        self.rdr.seek_to(info.position)?;
+        self.frame_parser
+            .restore_before_frame(frame_idx, info, entry.delay, nb_pixels_before);
+        match self.draw_next_frame()? {
+            Some(frame) if frame.0.position == info.position => Ok(frame),
+            // The GIF buffer changed since the index was built
+            _ => {
+                self.failed = true;
+                Err(GifParsingError::UnexpectedBlock {
+                    position: info.position,
+                })
+            }
+        }
+    }
+}
+
+impl<R: GifRead> Iterator for GifDecoder<R> {
+    type Item = Result<Frame>;
+
+    fn next(&mut self) -> Option<Result<Frame>> {
+        self.next_frame().transpose()
+    }
+}
+
+/// Create a `GifDecoder`, with the default options, for a GIF buffer which is
+/// already entirely in memory.
+///
+/// Compressed data is then never copied: image data is decompressed straight
+/// from `bytes`. Use `GifDecoder::with_options` with a `SliceReader` to give
+/// other options, noting that decoding on several threads (see
+/// `DecodeOptions::threads`) copies the image data of each frame to hand it to
+/// the worker threads.
+pub fn decode_from_bytes(bytes: &[u8]) -> Result<GifDecoder<SliceReader<'_>>> {
+    GifDecoder::new(SliceReader::new(bytes))
+}
+
+/// Iterator over the frames of a GIF buffer, as they are stored in it.
+/// Created through `GifDecoder::into_raw_frames`.
+pub struct RawFrames<R: GifRead> {
+    decoder: GifDecoder<R>,
+}
+
+impl<R: GifRead> RawFrames<R> {
+    /// Returns the underlying `GifDecoder`.
+    pub fn decoder(&self) -> &GifDecoder<R> {
+        &self.decoder
+    }
+}
+
+impl<R: GifRead> Iterator for RawFrames<R> {
+    type Item = Result<RawFrame>;
+
+    fn next(&mut self) -> Option<Result<RawFrame>> {
+        self.decoder.next_raw_frame().transpose()
+    }
+}
+
+/// Sends the events of a decoding through a channel, in order.
+pub(crate) struct EventSender {
+    tx: std::sync::mpsc::Sender<GifEvent>,
+    /// Set to `true` once the looping information has been sent.
+    sent_loop_attribute: bool,
+}
+
+impl EventSender {
+    pub(crate) fn new(tx: std::sync::mpsc::Sender<GifEvent>) -> EventSender {
+        EventSender {
+            tx,
+            sent_loop_attribute: false,
+        }
+    }
+
+    /// Send a decoded frame, preceded by the looping information if it was
+    /// not sent yet and by the comments and warnings encountered since the
+    /// last frame.
+    pub(crate) fn send_frame(
+        &mut self,
+        loop_count: Option<u16>,
+        comments: &[Comment],
+        warnings: &[Warning],
+        frame: Frame,
+    ) -> Result<()> {
+        if let (false, Some(x)) = (self.sent_loop_attribute, loop_count) {
+            self.sent_loop_attribute = true;
+            self.send(GifEvent::LoopingInfo(Some(x)))?;
+        }
+        self.send_all(comments, warnings)?;
+        self.send(GifEvent::Frame(frame))
+    }
+
+    /// Send the events signaling that all frames have been decoded, preceded
+    /// by the comments and warnings encountered since the last frame.
+    pub(crate) fn send_end(
+        &mut self,
+        loop_count: Option<u16>,
+        comments: &[Comment],
+        warnings: &[Warning],
+    ) -> Result<()> {
+        self.send_all(comments, warnings)?;
+        match loop_count {
+            Some(x) if !self.sent_loop_attribute => self.send(GifEvent::LoopingInfo(Some(x)))?,
+            None => self.send(GifEvent::LoopingInfo(None))?,
+            _ => {}
+        }
+        self.send(GifEvent::FrameEnd)
+    }
+
+    fn send_all(&self, comments: &[Comment], warnings: &[Warning]) -> Result<()> {
+        for comment in comments {
+            self.send(GifEvent::Comment(comment.clone()))?;
+        }
+        for warning in warnings {
+            self.send(GifEvent::Warning(warning.clone()))?;
+        }
+        Ok(())
+    }
+
+    fn send(&self, event: GifEvent) -> Result<()> {
+        self.tx
+            .send(event)
+            .map_err(|_| GifParsingError::EventChannelClosed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gif_reader::GifReader;
+    use crate::limits::{Limits, ResourceLimit};
+    use crate::parser::DisposalMethod;
+    use crate::test_utils::{build_animation, GifBuilder, PALETTE};
+    use std::cell::RefCell;
+    use std::io::{Read, Seek, SeekFrom};
+    use std::rc::Rc;
+
+    /// In-memory buffer which can still be modified once given to a reader.
+    struct SharedBuffer {
+        data: Rc<RefCell<Vec<u8>>>,
+        pos: usize,
+    }
+
+    impl Read for SharedBuffer {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let data = self.data.borrow();
+            let rest = data.get(self.pos..).unwrap_or(&[]);
+            let nb_bytes = rest.len().min(buf.len());
+            buf[..nb_bytes].copy_from_slice(&rest[..nb_bytes]);
+            self.pos += nb_bytes;
+            Ok(nb_bytes)
+        }
+    }
+
+    impl Seek for SharedBuffer {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            self.pos = match pos {
+                SeekFrom::Start(pos) => pos as usize,
+                SeekFrom::Current(offset) => (self.pos as i64 + offset) as usize,
+                SeekFrom::End(offset) => (self.data.borrow().len() as i64 + offset) as usize,
+            };
+            Ok(self.pos as u64)
+        }
+    }
+
+    /// Reader which is not seekable, like a pipe, returning at most 3 bytes
+    /// per read.
+    struct Pipe<'a>(&'a [u8]);
+
+    impl Read for Pipe<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let nb_bytes = self.0.len().min(buf.len()).min(3);
+            buf[..nb_bytes].copy_from_slice(&self.0[..nb_bytes]);
+            self.0 = &self.0[nb_bytes..];
+            Ok(nb_bytes)
+        }
+    }
+
+    #[test]
+    fn yields_raw_frames() {
+        let gif = build_animation();
+        let mut decoder = decode_from_bytes(&gif).unwrap();
+        let first = decoder.next_raw_frame().unwrap().unwrap();
+        assert_eq!(first.indices, [1, 1, 1, 1]);
+        assert_eq!(first.palette, PALETTE);
+
+        let raw_frames: Vec<_> = decoder.into_raw_frames().map(|f| f.unwrap()).collect();
+        let summary: Vec<_> = raw_frames
+            .iter()
+            .map(|f| {
+                let info = &f.info;
+                (
+                    (info.left, info.top, info.width, info.height),
+                    f.indices.clone(),
+                    info.disposal_method,
+                    info.transparent_color_index,
+                )
+            })
+            .collect();
+        // Only the frames' own rectangles, without compositing
+        assert_eq!(
+            summary,
+            [
+                (
+                    (0, 0, 1, 1),
+                    vec![2],
+                    DisposalMethod::RestoreToBackgroundColor,
+                    None
+                ),
+                (
+                    (1, 0, 1, 1),
+                    vec![3],
+                    DisposalMethod::RestoreToPrevious,
+                    None
+                ),
+                (
+                    (0, 0, 2, 2),
+                    vec![0, 2, 0, 3],
+                    DisposalMethod::DoNotDispose,
+                    Some(0)
+                ),
+            ]
+        );
+        assert!(raw_frames.iter().all(|f| f.delay == Some(10)));
+    }
+
+    #[test]
+    fn decodes_from_non_seekable_reader() {
+        // Ignored Application Extension, skipped without being read
+        let mut gif = GifBuilder::new(2, 2, 0)
+            .application_extension(b"UNKNOWN1.0\0", &[&[0; 40], &[0; 20]])
+            .build();
+        gif.pop();
+        gif.extend_from_slice(&build_animation()[25..]);
+        let expected: Vec<_> = decode_from_bytes(&gif)
+            .unwrap()
+            .map(|f| f.unwrap().data)
+            .collect();
+        assert_eq!(expected.len(), 4);
+
+        let decoder = GifDecoder::new(GifReader::new(Pipe(&gif))).unwrap();
+        let frames: Vec<_> = decoder.map(|f| f.unwrap().data).collect();
+        assert_eq!(frames, expected);
+
+        let decoder = GifDecoder::new(GifReader::new(Pipe(&gif[..gif.len() - 3]))).unwrap();
+        assert!(decoder.map(|f| f.map(|_| ())).any(|res| res.is_err()));
+    }
+
+    #[test]
+    fn from_parts_with_reader_starting_after_header() {
+        // The background color index is out of the Global Color Table
+        let gif = GifBuilder::new(2, 1, 7).image(0, 0, 2, 1, &[1, 2]).build();
+        let mut rdr = SliceReader::new(&gif);
+        let header = parser::parse_header(&mut rdr).unwrap();
+        let header_end = rdr.get_pos();
+
+        let rdr = SliceReader::new(&gif[header_end..]);
+        let mut decoder = GifDecoder::from_parts(rdr, header, DecodeOptions::default()).unwrap();
+        assert_eq!(
+            decoder.warnings(),
+            [Warning::InvalidBackgroundColorIndex {
+                index: 7,
+                position: 11
+            }]
+        );
+        let frame = decoder.next_frame().unwrap().unwrap();
+        assert_eq!(frame.data, [255, 255, 255, 255, 255, 0, 0, 255]);
+        assert!(decoder.next_frame().unwrap().is_none());
+    }
+
+    #[test]
+    fn seeking_renders_like_sequential_decoding() {
+        let gif = build_animation();
+        let frames: Vec<Frame> = decode_from_bytes(&gif)
+            .unwrap()
+            .map(|frame| frame.unwrap())
+            .collect();
+        assert_eq!(frames.len(), 4);
+        for (n, frame) in frames.iter().enumerate() {
+            let mut decoder = decode_from_bytes(&gif).unwrap();
+            let sought = decoder.seek_to_frame(n).unwrap().unwrap();
+            assert_eq!(sought.data, frame.data, "frame {}", n);
+        }
+
+        // Going back and forth in the same decoder
+        let mut decoder = decode_from_bytes(&gif).unwrap();
+        assert_eq!(
+            decoder.seek_to_frame(3).unwrap().unwrap().data,
+            frames[3].data
+        );
+        assert_eq!(
+            decoder.seek_to_frame(1).unwrap().unwrap().data,
+            frames[1].data
+        );
+        assert_eq!(decoder.next_frame().unwrap().unwrap().data, frames[2].data);
+        assert!(decoder.seek_to_frame(4).unwrap().is_none());
+    }
+
+    #[test]
+    fn frame_index_respects_limits() {
+        let gif = build_animation();
+        let options = DecodeOptions {
+            limits: Limits {
+                max_frames: Some(3),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut decoder = GifDecoder::with_options(SliceReader::new(&gif), options).unwrap();
+        assert!(matches!(
+            decoder.frame_index(),
+            Err(GifParsingError::LimitExceeded(ResourceLimit::Frames))
+        ));
+    }
+
+    #[test]
+    fn seeking_counts_limits_as_sequential_decoding() {
+        // Frames of 4, 1, 1 and 4 pixels
+        let gif = build_animation();
+        let options = DecodeOptions {
+            limits: Limits {
+                max_decoded_bytes: Some(10),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut decoder = GifDecoder::with_options(SliceReader::new(&gif), options).unwrap();
+        for _ in 0..3 {
+            assert!(decoder.seek_to_frame(3).unwrap().is_some());
+        }
+        assert!(decoder.seek_to_frame(0).unwrap().is_some());
+        for _ in 1..4 {
+            assert!(decoder.next_frame().unwrap().is_some());
+        }
+        assert!(decoder.next_frame().unwrap().is_none());
+
+        let options = DecodeOptions {
+            limits: Limits {
+                max_decoded_bytes: Some(9),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut decoder = GifDecoder::with_options(SliceReader::new(&gif), options).unwrap();
+        assert!(matches!(
+            decoder.seek_to_frame(0),
+            Err(GifParsingError::LimitExceeded(ResourceLimit::DecodedBytes))
+        ));
+    }
+
+    #[test]
+    fn only_copies_changed_part_of_canvas() {
+        let gif = GifBuilder::new(4, 4, 0)
+            .image(0, 0, 4, 4, &[1; 16])
+            .graphic_control(2, None, 0)
+            .image(1, 1, 1, 1, &[2])
+            .image(3, 3, 1, 1, &[3])
+            .build();
+        let options = DecodeOptions {
+            pixel_format: crate::color::PixelFormat::Indexed,
+            ..Default::default()
+        };
+        let mut decoder = GifDecoder::with_options(SliceReader::new(&gif), options).unwrap();
+        let mut frame = decoder.next_frame().unwrap().unwrap();
+        assert_eq!(frame.data, [1; 16]);
+
+        // Pixels out of the changed part keep whatever `frame` contained
+        frame.data = vec![9; 16];
+        assert!(decoder.next_frame_into(&mut frame).unwrap());
+        assert_eq!((frame.info.left, frame.info.top), (1, 1));
+        #[rustfmt::skip]
+        assert_eq!(frame.data, [
+            9, 9, 9, 9,
+            9, 2, 9, 9,
+            9, 9, 9, 9,
+            9, 9, 9, 9,
+        ]);
+
+        // The disposed rectangle of the previous frame is also copied
+        frame.data = vec![9; 16];
+        assert!(decoder.next_frame_into(&mut frame).unwrap());
+        #[rustfmt::skip]
+        assert_eq!(frame.data, [
+            9, 9, 9, 9,
+            9, 0, 1, 1,
+            9, 1, 1, 1,
+            9, 1, 1, 3,
+        ]);
+        assert_eq!(
+            decoder.compositor.canvas(),
+            [1, 1, 1, 1, 1, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 3]
+        );
+        assert!(!decoder.next_frame_into(&mut frame).unwrap());
+    }
+
+    #[test]
+    fn leaves_missing_pixels_untouched() {
+        let mut gif = GifBuilder::new(2, 2, 0)
+            .image(0, 0, 2, 2, &[1, 1, 1, 1])
+            .build();
+        gif.pop();
+        gif.extend_from_slice(&[0x2C, 0, 0, 0, 0, 2, 0, 2, 0, 0x00, 0x02]);
+        // `clear` code and `2`, then the first bits of another `2`
+        gif.extend_from_slice(&[0x01, 0x94, 0x00, 0x3B]);
+        let options = DecodeOptions {
+            lenient: true,
+            ..Default::default()
+        };
+        let white = [255, 255, 255, 255];
+        let mut expected = vec![255, 0, 0, 255];
+        expected.extend(white.iter().cycle().take(12));
+
+        let mut decoder =
+            GifDecoder::with_options(SliceReader::new(&gif), options.clone()).unwrap();
+        decoder.next_frame().unwrap().unwrap();
+        assert_eq!(decoder.next_frame().unwrap().unwrap().data, expected);
+        assert!(decoder.warnings().contains(&Warning::IncompleteFrame {
+            position: 41,
+            nb_decoded: 1,
+            nb_pixels: 4,
+        }));
+
+        let decoder = GifDecoder::with_options(SliceReader::new(&gif), options.clone()).unwrap();
+        let mut compositor = Compositor::from_header(decoder.header(), &options);
+        let raw_frames: Vec<RawFrame> = decoder.into_raw_frames().map(|f| f.unwrap()).collect();
+        assert_eq!(raw_frames[1].indices, [2, 0, 0, 0]);
+        assert_eq!(raw_frames[1].nb_decoded, 1);
+        compositor.composite(&raw_frames[0]);
+        assert_eq!(compositor.composite(&raw_frames[1]), &expected[..]);
+    }
+
+    #[test]
+    fn seeking_fails_if_buffer_changed() {
+        let data = Rc::new(RefCell::new(build_animation()));
+        let rdr = GifReader::new(SharedBuffer {
+            data: Rc::clone(&data),
+            pos: 0,
+        });
+        let mut decoder = GifDecoder::new(rdr).unwrap();
+        let position = decoder.frame_index().unwrap().entries()[2].info.position;
+
+        // Replace the frame by a Trailer
+        data.borrow_mut()[position] = 0x3B;
+        assert!(matches!(
+            decoder.seek_to_frame(2),
+            Err(GifParsingError::UnexpectedBlock { position: p }) if p == position
+        ));
+        assert!(decoder.next_frame().unwrap().is_none());
+    }
+}
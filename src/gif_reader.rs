@@ -24,6 +24,22 @@ pub trait GifRead {
     /// Return the next N bytes as a slice of u8.
     fn read_bytes(&mut self, nb_bytes: usize) -> Result<Vec<u8>, std::io::Error>;
 
+    /// Fill `buf` with the next bytes, without allocating.
+    fn read_into(&mut self, buf: &mut [u8]) -> Result<(), std::io::Error> {
+        let bytes = self.read_bytes(buf.len())?;
+        buf.copy_from_slice(&bytes);
+        Ok(())
+    }
+
+    /// Return the next `buf.len()` bytes, without allocating.
+    /// They are written to `buf` and returned from there, unless the reader
+    /// already has them in memory and can directly lend them (see
+    /// `SliceReader`), in which case `buf` is left untouched.
+    fn read_borrowed<'a>(&'a mut self, buf: &'a mut [u8]) -> Result<&'a [u8], std::io::Error> {
+        self.read_into(buf)?;
+        Ok(buf)
+    }
+
     /// Skip `nb_bytes` number of bytes.
     fn skip_bytes(&mut self, nb_bytes: usize) -> Result<(), std::io::Error>;
 
@@ -31,17 +47,69 @@ pub trait GifRead {
     fn get_pos(&self) -> usize;
 }
 
+impl<T: GifRead + ?Sized> GifRead for &mut T {
+    fn read_str(&mut self, nb_bytes: usize) -> Result<String, GifReaderStringError> {
+        (**self).read_str(nb_bytes)
+    }
+
+    fn read_u16(&mut self) -> Result<u16, std::io::Error> {
+        (**self).read_u16()
+    }
+
+    fn read_u8(&mut self) -> Result<u8, std::io::Error> {
+        (**self).read_u8()
+    }
+
+    fn read_bytes(&mut self, nb_bytes: usize) -> Result<Vec<u8>, std::io::Error> {
+        (**self).read_bytes(nb_bytes)
+    }
+
+    fn read_into(&mut self, buf: &mut [u8]) -> Result<(), std::io::Error> {
+        (**self).read_into(buf)
+    }
+
+    fn read_borrowed<'a>(&'a mut self, buf: &'a mut [u8]) -> Result<&'a [u8], std::io::Error> {
+        (**self).read_borrowed(buf)
+    }
+
+    fn skip_bytes(&mut self, nb_bytes: usize) -> Result<(), std::io::Error> {
+        (**self).skip_bytes(nb_bytes)
+    }
+
+    fn get_pos(&self) -> usize {
+        (**self).get_pos()
+    }
+}
+
+/// The GifSeek trait is implemented by `GifRead` types which can also be moved
+/// to any position in the GIF buffer.
+pub trait GifSeek: GifRead {
+    /// Move the cursor to the position `pos` in the GIF buffer.
+    fn seek_to(&mut self, pos: usize) -> Result<(), std::io::Error>;
+}
+
+impl<T: GifSeek + ?Sized> GifSeek for &mut T {
+    fn seek_to(&mut self, pos: usize) -> Result<(), std::io::Error> {
+        (**self).seek_to(pos)
+    }
+}
+
 /// Struct helping with the parsing of the different values encountered in a GIF
 /// image file.
 /// This struct provides methods to consume and parse the N next bytes into the
 /// wanted "format" (e.g. ASCII, u16, u8...).
 /// Please not that this struct does no image decoding, you will also need a
 /// decoder to make sense of GIF image data.
-pub struct GifReader<T: Read + Seek> {
+///
+/// Any `Read` type can be used, even non-seekable ones such as stdin, pipes
+/// or sockets: skipped bytes are just read and discarded.
+pub struct GifReader<T: Read> {
     /// Reader returning the GIF buffer
     reader: T,
     /// Current position in the GIF file.
     pos: usize,
+    /// Position in the GIF file at which `reader` started.
+    start_pos: usize,
 }
 
 /// Errors triggered when reading a string from a GIF buffer
@@ -52,14 +120,29 @@ pub enum GifReaderStringError {
     IOError(std::io::Error),
 }
 
-impl<T: Read + Seek> GifReader<T> {
+impl<T: Read> GifReader<T> {
     /// Create a new GifReader from the given GIF buffer.
     pub fn new(reader: T) -> GifReader<T> {
-        GifReader { reader, pos: 0 }
+        GifReader {
+            reader,
+            pos: 0,
+            start_pos: 0,
+        }
+    }
+
+    /// Create a new GifReader from a reader which does not start at the
+    /// beginning of the GIF buffer but at the position `pos` in it, so that
+    /// reported positions are still relative to the whole GIF buffer.
+    pub fn with_position(reader: T, pos: usize) -> GifReader<T> {
+        GifReader {
+            reader,
+            pos,
+            start_pos: pos,
+        }
     }
 }
 
-impl<T: Read + Seek> GifRead for GifReader<T> {
+impl<T: Read> GifRead for GifReader<T> {
     /// Read the next N bytes as an utf8 string.
     /// TODO GIF strings always seem to be in ASCII.
     /// Here I'm left with a dilemma:
@@ -105,11 +188,25 @@ impl<T: Read + Seek> GifRead for GifReader<T> {
         Ok(buffer)
     }
 
+    /// Fill `buf` with the next bytes, without allocating.
+    fn read_into(&mut self, buf: &mut [u8]) -> Result<(), std::io::Error> {
+        self.pos += buf.len();
+        self.reader.read_exact(buf)
+    }
+
     /// Skip `nb_bytes` number of bytes.
     fn skip_bytes(&mut self, nb_bytes: usize) -> Result<(), std::io::Error> {
         self.pos += nb_bytes;
-        self.reader
-            .seek(std::io::SeekFrom::Start(self.pos as u64))?;
+        let skipped = std::io::copy(
+            &mut (&mut self.reader).take(nb_bytes as u64),
+            &mut std::io::sink(),
+        )?;
+        if skipped < nb_bytes as u64 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "failed to skip the whole amount of bytes",
+            ));
+        }
         Ok(())
     }
 
@@ -118,3 +215,137 @@ impl<T: Read + Seek> GifRead for GifReader<T> {
         self.pos
     }
 }
+
+impl<T: Read + Seek> GifSeek for GifReader<T> {
+    /// Move the cursor to the position `pos` in the GIF buffer.
+    fn seek_to(&mut self, pos: usize) -> Result<(), std::io::Error> {
+        if pos < self.start_pos {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "cannot seek before the start of the reader",
+            ));
+        }
+        self.reader
+            .seek(std::io::SeekFrom::Start((pos - self.start_pos) as u64))?;
+        self.pos = pos;
+        Ok(())
+    }
+}
+
+/// `GifRead` implementation over a GIF buffer which is already entirely in
+/// memory.
+///
+/// Unlike a `GifReader` over a `std::io::Cursor`, parts of the buffer (image
+/// data sub-blocks, color tables...) are lent through `read_borrowed` instead
+/// of being copied, and moving in it is free.
+pub struct SliceReader<'a> {
+    /// The part of the GIF buffer to read.
+    data: &'a [u8],
+    /// Current position in `data`. May go past its end once a read failed.
+    offset: usize,
+    /// Position in the GIF buffer at which `data` starts.
+    start_pos: usize,
+}
+
+impl<'a> SliceReader<'a> {
+    /// Create a new SliceReader from the given GIF buffer.
+    pub fn new(data: &'a [u8]) -> SliceReader<'a> {
+        SliceReader::with_position(data, 0)
+    }
+
+    /// Create a new SliceReader from a part of a GIF buffer, which starts at
+    /// the position `pos` in it, so that reported positions are still
+    /// relative to the whole GIF buffer.
+    pub fn with_position(data: &'a [u8], pos: usize) -> SliceReader<'a> {
+        SliceReader {
+            data,
+            offset: 0,
+            start_pos: pos,
+        }
+    }
+
+    /// Return the next `nb_bytes` bytes, borrowed from the GIF buffer.
+    pub fn read_slice(&mut self, nb_bytes: usize) -> Result<&'a [u8], std::io::Error> {
+        // Like a `GifReader`, consider that the bytes are read even on error
+        let start = self.offset;
+        self.offset = self.offset.saturating_add(nb_bytes);
+        match self.data.get(start..self.offset) {
+            Some(bytes) => Ok(bytes),
+            None => Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "failed to fill whole buffer",
+            )),
+        }
+    }
+}
+
+impl GifRead for SliceReader<'_> {
+    /// Read the next N bytes as an utf8 string.
+    fn read_str(&mut self, nb_bytes: usize) -> Result<String, GifReaderStringError> {
+        let bytes = self
+            .read_slice(nb_bytes)
+            .map_err(GifReaderStringError::IOError)?;
+        match std::str::from_utf8(bytes) {
+            Err(_) => Err(GifReaderStringError::FromUtf8Error),
+            Ok(x) => Ok(x.to_owned()),
+        }
+    }
+
+    /// Get the next two bytes as an u16.
+    fn read_u16(&mut self) -> Result<u16, std::io::Error> {
+        let bytes = self.read_slice(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// Get the next byte.
+    fn read_u8(&mut self) -> Result<u8, std::io::Error> {
+        Ok(self.read_slice(1)?[0])
+    }
+
+    /// Return the next N bytes as a slice of u8.
+    fn read_bytes(&mut self, nb_bytes: usize) -> Result<Vec<u8>, std::io::Error> {
+        Ok(self.read_slice(nb_bytes)?.to_vec())
+    }
+
+    /// Fill `buf` with the next bytes, without allocating.
+    fn read_into(&mut self, buf: &mut [u8]) -> Result<(), std::io::Error> {
+        buf.copy_from_slice(self.read_slice(buf.len())?);
+        Ok(())
+    }
+
+    /// Return the next `buf.len()` bytes, borrowed from the GIF buffer: `buf`
+    /// is not used.
+    fn read_borrowed<'b>(&'b mut self, buf: &'b mut [u8]) -> Result<&'b [u8], std::io::Error> {
+        self.read_slice(buf.len())
+    }
+
+    /// Skip `nb_bytes` number of bytes.
+    fn skip_bytes(&mut self, nb_bytes: usize) -> Result<(), std::io::Error> {
+        match self.read_slice(nb_bytes) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "failed to skip the whole amount of bytes",
+            )),
+        }
+    }
+
+    /// Get the SliceReader's current cursor position
+    fn get_pos(&self) -> usize {
+        self.start_pos + self.offset
+    }
+}
+
+impl GifSeek for SliceReader<'_> {
+    /// Move the cursor to the position `pos` in the GIF buffer.
+    fn seek_to(&mut self, pos: usize) -> Result<(), std::io::Error> {
+        if pos < self.start_pos {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "cannot seek before the start of the reader",
+            ));
+        }
+        self.offset = pos - self.start_pos;
+        Ok(())
+    }
+}
@@ -0,0 +1,436 @@
+use crate::color::Rgb;
+
+/// Size, in bytes, of the header of an ICC profile.
+const HEADER_SIZE: usize = 128;
+
+/// Conversion from the XYZ color space, relative to the D50 white point used
+/// by ICC profiles, to linear sRGB (Bradford chromatic adaptation included).
+const XYZ_D50_TO_LINEAR_SRGB: [[f32; 3]; 3] = [
+    [3.133_856, -1.616_867, -0.490_615],
+    [-0.978_768, 1.916_142, 0.033_454],
+    [0.071_945, -0.228_991, 1.405_243],
+];
+
+/// Conversion of colors described by an ICC color profile to sRGB.
+///
+/// Only RGB "matrix/TRC" profiles are supported: each channel goes through its
+/// own Tone Reproduction Curve, then through a matrix converting them to the
+/// XYZ color space.
+pub(crate) struct IccTransform {
+    /// Linear value of each channel, for each of the 256 possible values.
+    linear: [[f32; 256]; 3],
+
+    /// Conversion from the linear channels of the profile to linear sRGB.
+    matrix: [[f32; 3]; 3],
+}
+
+impl IccTransform {
+    /// Parse the given ICC profile.
+    /// Returns `None` if it is invalid or not a supported matrix/TRC RGB
+    /// profile.
+    pub(crate) fn parse(profile: &[u8]) -> Option<IccTransform> {
+        if profile.len() < HEADER_SIZE + 4
+            || &profile[16..20] != b"RGB "
+            || &profile[20..24] != b"XYZ "
+        {
+            return None;
+        }
+        // Each entry of the tag table takes 12 bytes
+        let nb_tags = read_u32(profile, HEADER_SIZE)? as usize;
+        if nb_tags > (profile.len() - HEADER_SIZE - 4) / 12 {
+            return None;
+        }
+        let find_tag = |signature: &[u8]| -> Option<&[u8]> {
+            (0..nb_tags).find_map(|i| {
+                let entry = HEADER_SIZE + 4 + i * 12;
+                if profile.get(entry..entry + 4)? != signature {
+                    return None;
+                }
+                let offset = read_u32(profile, entry + 4)? as usize;
+                let size = read_u32(profile, entry + 8)? as usize;
+                profile.get(offset..offset.checked_add(size)?)
+            })
+        };
+
+        let mut to_xyz = [[0.; 3]; 3];
+        let mut linear = [[0.; 256]; 3];
+        let channels = [(b"rXYZ", b"rTRC"), (b"gXYZ", b"gTRC"), (b"bXYZ", b"bTRC")];
+        for (channel, (xyz_tag, trc_tag)) in channels.iter().enumerate() {
+            let xyz = parse_xyz(find_tag(&xyz_tag[..])?)?;
+            for (row, value) in xyz.iter().enumerate() {
+                to_xyz[row][channel] = *value;
+            }
+            let curve = Curve::parse(find_tag(&trc_tag[..])?)?;
+            for (value, linear_value) in linear[channel].iter_mut().enumerate() {
+                *linear_value = curve.apply(value as f32 / 255.);
+            }
+        }
+
+        let mut matrix = [[0.; 3]; 3];
+        for (row, matrix_row) in matrix.iter_mut().enumerate() {
+            for (col, value) in matrix_row.iter_mut().enumerate() {
+                *value = (0..3)
+                    .map(|k| XYZ_D50_TO_LINEAR_SRGB[row][k] * to_xyz[k][col])
+                    .sum();
+            }
+        }
+        Some(IccTransform { linear, matrix })
+    }
+
+    /// Convert a color described by the profile to sRGB.
+    pub(crate) fn convert(&self, color: Rgb) -> Rgb {
+        let linear = [
+            self.linear[0][color.r as usize],
+            self.linear[1][color.g as usize],
+            self.linear[2][color.b as usize],
+        ];
+        let channel = |row: usize| {
+            let value: f32 = (0..3).map(|k| self.matrix[row][k] * linear[k]).sum();
+            (encode_srgb(value.clamp(0., 1.)) * 255.).round() as u8
+        };
+        Rgb {
+            r: channel(0),
+            g: channel(1),
+            b: channel(2),
+        }
+    }
+
+    /// Convert every color of a color table described by the profile to sRGB.
+    pub(crate) fn convert_palette(&self, palette: &mut [Rgb]) {
+        for color in palette.iter_mut() {
+            *color = self.convert(*color);
+        }
+    }
+}
+
+/// A Tone Reproduction Curve, converting the value of a channel (from `0` to
+/// `1`) to a linear value.
+enum Curve {
+    /// `y = x ^ gamma`.
+    Gamma(f32),
+
+    /// Values evenly sampled from `0` to `1`, linearly interpolated.
+    Table(Vec<f32>),
+
+    /// A parametric curve, with the parameters `g, a, b, c, d, e, f` as
+    /// defined for the function type 4 of the ICC specification, to which the
+    /// other function types can be reduced.
+    Parametric([f32; 7]),
+}
+
+impl Curve {
+    /// Parse a `curv` or `para` tag.
+    fn parse(tag: &[u8]) -> Option<Curve> {
+        match tag.get(0..4)? {
+            b"curv" => {
+                let count = read_u32(tag, 8)? as usize;
+                let values: Vec<f32> = (0..count)
+                    .map(|i| read_u16(tag, 12 + i * 2).map(|v| v as f32))
+                    .collect::<Option<_>>()?;
+                match count {
+                    0 => Some(Curve::Gamma(1.)),
+                    1 => Some(Curve::Gamma(values[0] / 256.)),
+                    _ => Some(Curve::Table(values.iter().map(|v| v / 65535.).collect())),
+                }
+            }
+            b"para" => {
+                let nb_params = match read_u16(tag, 8)? {
+                    0 => 1,
+                    1 => 3,
+                    2 => 4,
+                    3 => 5,
+                    4 => 7,
+                    _ => return None,
+                };
+                let mut p = [0.; 7];
+                for (i, param) in p.iter_mut().enumerate().take(nb_params) {
+                    *param = read_s15_fixed16(tag, 12 + i * 4)?;
+                }
+                let [g, a, b, c, d, e, f] = p;
+                // Express every function type as the type 4
+                let params = match nb_params {
+                    1 => [g, 1., 0., 0., 0., 0., 0.],
+                    3 => [g, a, b, 0., -b / a, 0., 0.],
+                    4 => [g, a, b, 0., -b / a, c, c],
+                    5 => [g, a, b, c, d, 0., 0.],
+                    _ => [g, a, b, c, d, e, f],
+                };
+                Some(Curve::Parametric(params))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the linear value corresponding to the value `x`, from `0` to
+    /// `1`.
+    fn apply(&self, x: f32) -> f32 {
+        match self {
+            Curve::Gamma(gamma) => x.powf(*gamma),
+            Curve::Table(values) => {
+                let pos = x * (values.len() - 1) as f32;
+                let idx = (pos.floor() as usize).min(values.len() - 2);
+                let frac = pos - idx as f32;
+                values[idx] * (1. - frac) + values[idx + 1] * frac
+            }
+            Curve::Parametric([g, a, b, c, d, e, f]) => {
+                if x >= *d {
+                    (a * x + b).max(0.).powf(*g) + e
+                } else {
+                    c * x + f
+                }
+            }
+        }
+    }
+}
+
+/// Parse a `XYZ ` tag, and return its first X, Y and Z values.
+fn parse_xyz(tag: &[u8]) -> Option<[f32; 3]> {
+    if tag.get(0..4)? != b"XYZ " {
+        return None;
+    }
+    Some([
+        read_s15_fixed16(tag, 8)?,
+        read_s15_fixed16(tag, 12)?,
+        read_s15_fixed16(tag, 16)?,
+    ])
+}
+
+/// Apply the sRGB transfer function to a linear value, from `0` to `1`.
+fn encode_srgb(value: f32) -> f32 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1. / 2.4) - 0.055
+    }
+}
+
+/// Read the big-endian `u16` at the given offset, if in bounds.
+fn read_u16(buf: &[u8], offset: usize) -> Option<u16> {
+    let bytes = buf.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+/// Read the big-endian `u32` at the given offset, if in bounds.
+fn read_u32(buf: &[u8], offset: usize) -> Option<u32> {
+    let bytes = buf.get(offset..offset + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Read the `s15Fixed16Number` at the given offset, if in bounds.
+fn read_s15_fixed16(buf: &[u8], offset: usize) -> Option<f32> {
+    read_u32(buf, offset).map(|v| v as i32 as f32 / 65536.)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gif_decoder::GifDecoder;
+    use crate::gif_reader::SliceReader;
+    use crate::options::DecodeOptions;
+    use crate::test_utils::GifBuilder;
+
+    /// Colorants of the red, green and blue channels of sRGB, adapted to the
+    /// D50 white point, as found in sRGB ICC profiles.
+    const SRGB_COLORANTS: [[f32; 3]; 3] = [
+        [0.436_1, 0.222_5, 0.013_9],
+        [0.385_1, 0.716_9, 0.097_1],
+        [0.143_1, 0.060_6, 0.714_1],
+    ];
+
+    /// Build a RGB matrix/TRC profile with the given colorants (XYZ values of
+    /// its red, green and blue channels) and Tone Reproduction Curve tag, used
+    /// for every channel.
+    fn build_profile_with(colorants: [[f32; 3]; 3], trc_tag: Vec<u8>) -> Vec<u8> {
+        let mut profile = vec![0; HEADER_SIZE];
+        profile[16..20].copy_from_slice(b"RGB ");
+        profile[20..24].copy_from_slice(b"XYZ ");
+        let tags: [(&[u8], Vec<u8>); 6] = [
+            (b"rXYZ", xyz_tag(colorants[0])),
+            (b"gXYZ", xyz_tag(colorants[1])),
+            (b"bXYZ", xyz_tag(colorants[2])),
+            (b"rTRC", trc_tag.clone()),
+            (b"gTRC", trc_tag.clone()),
+            (b"bTRC", trc_tag),
+        ];
+        profile.extend_from_slice(&(tags.len() as u32).to_be_bytes());
+        let mut offset = HEADER_SIZE + 4 + tags.len() * 12;
+        for (signature, data) in &tags {
+            profile.extend_from_slice(signature);
+            profile.extend_from_slice(&(offset as u32).to_be_bytes());
+            profile.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            offset += data.len();
+        }
+        for (_, data) in &tags {
+            profile.extend_from_slice(data);
+        }
+        profile
+    }
+
+    /// Build a RGB matrix/TRC profile whose primaries are the X, Y and Z axes
+    /// and whose curves are linear.
+    fn build_profile() -> Vec<u8> {
+        let axes = [[1., 0., 0.], [0., 1., 0.], [0., 0., 1.]];
+        build_profile_with(axes, curv_tag(&[]))
+    }
+
+    fn xyz_tag(values: [f32; 3]) -> Vec<u8> {
+        let mut tag = b"XYZ \0\0\0\0".to_vec();
+        for value in &values {
+            tag.extend_from_slice(&((value * 65536.).round() as i32).to_be_bytes());
+        }
+        tag
+    }
+
+    fn curv_tag(values: &[u16]) -> Vec<u8> {
+        let mut tag = b"curv\0\0\0\0".to_vec();
+        tag.extend_from_slice(&(values.len() as u32).to_be_bytes());
+        for value in values {
+            tag.extend_from_slice(&value.to_be_bytes());
+        }
+        tag
+    }
+
+    fn para_tag(function_type: u16, params: &[f32]) -> Vec<u8> {
+        let mut tag = b"para\0\0\0\0".to_vec();
+        tag.extend_from_slice(&function_type.to_be_bytes());
+        tag.extend_from_slice(&[0, 0]);
+        for param in params {
+            tag.extend_from_slice(&((param * 65536.).round() as i32).to_be_bytes());
+        }
+        tag
+    }
+
+    /// Check that `curve` gives approximately `expected` for each value.
+    fn assert_curve(curve: &Curve, expected: &[(f32, f32)]) {
+        for &(x, y) in expected {
+            let value = curve.apply(x);
+            assert!(
+                (value - y).abs() < 1e-3,
+                "{} gave {} instead of {}",
+                x,
+                value,
+                y
+            );
+        }
+    }
+
+    #[test]
+    fn parses_matrix_trc_profile() {
+        let transform = IccTransform::parse(&build_profile()).unwrap();
+        let black = Rgb { r: 0, g: 0, b: 0 };
+        assert_eq!(transform.convert(black), black);
+    }
+
+    #[test]
+    fn applies_curv_curves() {
+        // Gamma of 2, as an u8Fixed8Number
+        let curve = Curve::parse(&curv_tag(&[0x0200])).unwrap();
+        assert_curve(&curve, &[(0., 0.), (0.5, 0.25), (0.8, 0.64), (1., 1.)]);
+
+        let curve = Curve::parse(&curv_tag(&[])).unwrap();
+        assert_curve(&curve, &[(0., 0.), (0.3, 0.3), (1., 1.)]);
+
+        // Interpolated in between the values of the table
+        let curve = Curve::parse(&curv_tag(&[0, 0x4000, 0xFFFF])).unwrap();
+        assert_curve(
+            &curve,
+            &[
+                (0., 0.),
+                (0.25, 0.125),
+                (0.5, 0.25),
+                (0.75, 0.625),
+                (1., 1.),
+            ],
+        );
+
+        assert!(Curve::parse(&curv_tag(&[0, 0x4000])[..14]).is_none());
+    }
+
+    #[test]
+    fn applies_para_curves() {
+        let curve = Curve::parse(&para_tag(0, &[2.])).unwrap();
+        assert_curve(&curve, &[(0.5, 0.25), (1., 1.)]);
+
+        // The sRGB transfer function
+        let params = [2.4, 1. / 1.055, 0.055 / 1.055, 1. / 12.92, 0.040_45];
+        let curve = Curve::parse(&para_tag(3, &params)).unwrap();
+        assert_curve(
+            &curve,
+            &[(0., 0.), (0.02, 0.001_548), (0.5, 0.214_041), (1., 1.)],
+        );
+
+        assert!(Curve::parse(&para_tag(5, &params)).is_none());
+        assert!(Curve::parse(&para_tag(3, &params[..4])).is_none());
+    }
+
+    #[test]
+    fn converts_colors_to_srgb() {
+        let gray = Rgb {
+            r: 128,
+            g: 128,
+            b: 128,
+        };
+        let red = Rgb { r: 255, g: 0, b: 0 };
+
+        // sRGB with linear curves: only the transfer function changes
+        let transform =
+            IccTransform::parse(&build_profile_with(SRGB_COLORANTS, curv_tag(&[]))).unwrap();
+        assert_eq!(
+            transform.convert(gray),
+            Rgb {
+                r: 188,
+                g: 188,
+                b: 188
+            }
+        );
+        assert_eq!(transform.convert(red), red);
+
+        // sRGB with its red and green channels swapped
+        let [r, g, b] = SRGB_COLORANTS;
+        let profile = build_profile_with([g, r, b], curv_tag(&[0x0233]));
+        let transform = IccTransform::parse(&profile).unwrap();
+        assert_eq!(transform.convert(red), Rgb { r: 0, g: 255, b: 0 });
+        let mut palette = [red, Rgb { r: 0, g: 0, b: 255 }];
+        transform.convert_palette(&mut palette);
+        assert_eq!(
+            palette,
+            [Rgb { r: 0, g: 255, b: 0 }, Rgb { r: 0, g: 0, b: 255 }]
+        );
+    }
+
+    #[test]
+    fn applies_color_profile_when_decoding() {
+        // sRGB with its red and green channels swapped
+        let [r, g, b] = SRGB_COLORANTS;
+        let profile = build_profile_with([g, r, b], curv_tag(&[]));
+        let sub_blocks: Vec<&[u8]> = profile.chunks(255).collect();
+        let gif = GifBuilder::new(2, 1, 0)
+            .application_extension(b"ICCRGBG1012", &sub_blocks)
+            .image(0, 0, 2, 1, &[0, 2])
+            .build();
+        let decode = |apply_color_profile: bool| {
+            let options = DecodeOptions {
+                apply_color_profile,
+                ..Default::default()
+            };
+            let mut decoder = GifDecoder::with_options(SliceReader::new(&gif), options).unwrap();
+            let frame = decoder.next_frame().unwrap().unwrap();
+            assert_eq!(decoder.icc_profile(), Some(&profile[..]));
+            frame.data
+        };
+        assert_eq!(decode(false), [0, 0, 0, 255, 255, 0, 0, 255]);
+        assert_eq!(decode(true), [0, 0, 0, 255, 0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn rejects_tag_table_not_fitting_in_profile() {
+        let mut profile = build_profile();
+        profile[HEADER_SIZE..HEADER_SIZE + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(IccTransform::parse(&profile).is_none());
+
+        // One more entry than there is room for
+        let nb_tags = (profile.len() - HEADER_SIZE - 4) / 12 + 1;
+        profile[HEADER_SIZE..HEADER_SIZE + 4].copy_from_slice(&(nb_tags as u32).to_be_bytes());
+        assert!(IccTransform::parse(&profile).is_none());
+    }
+}
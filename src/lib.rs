@@ -0,0 +1,113 @@
+//! GIF decoding library, compatible with both the 87a and 89a GIF versions.
+//!
+//! The main entry point is the [`GifDecoder`], which parses the header of a
+//! GIF buffer on creation and can then decode the frames it contains:
+//!
+//! ```no_run
+//! use gif_renderer::{GifDecoder, GifReader};
+//!
+//! let file = std::fs::File::open("some-file.gif").unwrap();
+//! let rdr = GifReader::new(std::io::BufReader::new(file));
+//! let decoder = GifDecoder::new(rdr).unwrap();
+//! println!("{}x{}", decoder.width(), decoder.height());
+//!
+//! for frame in decoder {
+//!     let frame = frame.unwrap();
+//!     println!("New frame with a delay of {:?}", frame.delay);
+//! }
+//! ```
+//!
+//! When the GIF buffer is already in memory, `decode_from_bytes` creates a
+//! `GifDecoder` over a `SliceReader` instead, which decompresses image data
+//! straight from it, without copying it. This does not apply to
+//! `GifDecoder::decode` with `DecodeOptions::threads` set, which copies the
+//! image data of each frame to hand it to the worker threads.
+//!
+//! How frames are rendered can be configured by creating the `GifDecoder`
+//! through `GifDecoder::with_options` instead, with the wanted
+//! `DecodeOptions`.
+//!
+//! Each frame returned contains a copy of the whole canvas. To avoid copying
+//! it for every frame, `GifDecoder::next_frame_into` reuses the buffer of the
+//! previous frame instead, only copying to it the part of the canvas which
+//! changed.
+//!
+//! Frames are decoded lazily as they are requested. If you would prefer to
+//! receive them from another thread as they are decoded, `GifDecoder::decode`
+//! sends them as `GifEvent`s through a channel instead. It can decompress the
+//! image data of several frames in parallel, on the number of threads set in
+//! `DecodeOptions::threads`.
+//!
+//! When the GIF buffer is received in chunks (e.g. from the network) and you
+//! do not want to block waiting for the next one, a push-based
+//! `StreamingDecoder` can be fed those chunks as they come instead.
+//!
+//! When the reader is seekable, a `FrameIndex` of every frame can be built
+//! without decoding any image data, and any frame can then be directly reached
+//! through `GifDecoder::seek_to_frame`.
+//!
+//! Frames can also be obtained as they are stored in the GIF buffer - their own
+//! rectangle as color indices, along with their color table - through
+//! `GifDecoder::next_raw_frame` or `GifDecoder::into_raw_frames`. Those can
+//! then be rendered by a `Compositor`.
+//!
+//! When only metadata is needed (dimensions, frame count, duration...),
+//! `probe` summarizes a GIF buffer as a `GifInfo` without decoding any image
+//! data (`probe_with_options` applying resource limits to it). Enabling the
+//! `serde` feature makes that summary serializable.
+//!
+//! Known Application Extensions (looping, XMP packets, ICC color profiles...)
+//! are parsed by the decoders. Handlers can be registered through
+//! `GifDecoder::register_application_handler` to get the data of the other
+//! ones.
+//!
+//! Damaged GIFs (truncated, with unknown blocks...) can be decoded as far as
+//! possible by setting `DecodeOptions::lenient`, what was recovered from being
+//! reported as `Warning`s. Setting `DecodeOptions::strict` also reports
+//! deviations from the specification which are harmless to decoding.
+//!
+//! When decoding untrusted GIFs, the resources decoding may use (canvas size,
+//! number of frames, decompressed data, time...) can be bounded through
+//! `DecodeOptions::limits`.
+//!
+//! Lower-level building blocks (the header parser, the LZW decoder...) are also
+//! exported for users needing more control.
+
+mod application_extension;
+mod color;
+mod compositor;
+mod decoder;
+mod error;
+mod frame_index;
+mod gif_decoder;
+mod gif_reader;
+mod icc;
+mod limits;
+mod options;
+mod parallel;
+mod parser;
+mod plain_text;
+mod probe;
+mod streaming;
+#[cfg(test)]
+mod test_utils;
+mod warning;
+
+pub use application_extension::ApplicationExtension;
+pub use color::{PixelFormat, Rgb};
+pub use compositor::Compositor;
+pub use decoder::{LzwDecoder, LzwError, LzwProgress};
+pub use error::{GifParsingError, Result};
+pub use frame_index::{FrameIndex, FrameIndexEntry};
+pub use gif_decoder::{decode_from_bytes, GifDecoder, RawFrames};
+pub use gif_reader::{GifRead, GifReader, GifReaderStringError, GifSeek, SliceReader};
+pub use limits::{Limits, ResourceLimit};
+pub use options::{DecodeOptions, TransparencyMode};
+pub use parser::{
+    decode, parse_header, Comment, DisposalMethod, ExtensionKind, Frame, FrameInfo, GifEvent,
+    GifHeader, GifVersion, RawFrame,
+};
+pub use plain_text::PlainText;
+pub use probe::{probe, probe_with_options, GifInfo, ProbedFrame};
+pub use streaming::StreamingDecoder;
+pub use warning::Warning;
@@ -0,0 +1,294 @@
+use crate::compositor::square_pixels_size;
+use crate::error::{GifParsingError, Result};
+use crate::parser::GifHeader;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Limits on the resources decoding a GIF buffer may use, to protect against
+/// malicious GIFs (e.g. "decompression bombs": tiny GIFs declaring a huge
+/// logical screen or thousands of frames).
+///
+/// Exceeding one of them makes decoding fail with a
+/// `GifParsingError::LimitExceeded` error. Limits on sizes are checked before
+/// the corresponding memory is allocated.
+///
+/// Every limit is disabled (`None`) by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Limits {
+    /// Maximum number of pixels of the logical screen - and thus of decoded
+    /// frames, once resampled to square pixels if asked to - and of each
+    /// frame's own rectangle.
+    pub max_canvas_pixels: Option<u64>,
+
+    /// Maximum number of frames.
+    pub max_frames: Option<usize>,
+
+    /// Maximum number of bytes the image data of all frames may decompress
+    /// to, one byte corresponding to one pixel of a frame's own rectangle.
+    pub max_decoded_bytes: Option<u64>,
+
+    /// Maximum ratio between the number of pixels decompressed from the image
+    /// data of a frame and its size, in bytes, once compressed.
+    /// Note that legitimate frames made of a single color can already reach a
+    /// ratio of several hundreds.
+    pub max_lzw_ratio: Option<u32>,
+
+    /// Maximum time decoding may take.
+    ///
+    /// Only the time spent in the methods of the decoder which decode the GIF
+    /// buffer is counted, not the time between two calls to them.
+    pub max_duration: Option<Duration>,
+
+    /// Maximum size, in bytes, of an extension a `StreamingDecoder` may keep
+    /// in memory while waiting for it to be entirely fed.
+    pub max_extension_size: Option<usize>,
+}
+
+/// The different limits of `Limits`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceLimit {
+    /// See `Limits::max_canvas_pixels`.
+    CanvasPixels,
+
+    /// See `Limits::max_frames`.
+    Frames,
+
+    /// See `Limits::max_decoded_bytes`.
+    DecodedBytes,
+
+    /// See `Limits::max_lzw_ratio`.
+    LzwRatio,
+
+    /// See `Limits::max_duration`.
+    Duration,
+
+    /// See `Limits::max_extension_size`.
+    ExtensionSize,
+}
+
+impl fmt::Display for ResourceLimit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ResourceLimit::CanvasPixels => write!(f, "maximum number of canvas pixels"),
+            ResourceLimit::Frames => write!(f, "maximum number of frames"),
+            ResourceLimit::DecodedBytes => write!(f, "maximum number of decoded bytes"),
+            ResourceLimit::LzwRatio => write!(f, "maximum LZW compression ratio"),
+            ResourceLimit::Duration => write!(f, "maximum decoding duration"),
+            ResourceLimit::ExtensionSize => write!(f, "maximum size of an extension"),
+        }
+    }
+}
+
+impl Limits {
+    /// Check that the canvas of the GIF whose header is given, as well as the
+    /// frames rendered from it (resampled if `square_pixels` is set), can be
+    /// allocated.
+    pub(crate) fn check_screen(&self, header: &GifHeader, square_pixels: bool) -> Result<()> {
+        let (width, height) = (header.width as usize, header.height as usize);
+        // Resampled frames are never smaller than the canvas
+        let (width, height) = match header.pixel_aspect_ratio() {
+            Some(ratio) if square_pixels => square_pixels_size(width, height, ratio),
+            _ => (width, height),
+        };
+        self.check_canvas(width, height)
+    }
+
+    /// Check that a canvas (or frame) of `width` x `height` pixels can be
+    /// allocated.
+    pub(crate) fn check_canvas(&self, width: usize, height: usize) -> Result<()> {
+        check(
+            self.max_canvas_pixels,
+            width as u64 * height as u64,
+            ResourceLimit::CanvasPixels,
+        )
+    }
+
+    /// Check that `nb_frames` frames can be decoded.
+    pub(crate) fn check_frames(&self, nb_frames: usize) -> Result<()> {
+        check(self.max_frames, nb_frames, ResourceLimit::Frames)
+    }
+
+    /// Check that `nb_bytes` bytes of image data can be decompressed in total.
+    pub(crate) fn check_decoded_bytes(&self, nb_bytes: u64) -> Result<()> {
+        check(
+            self.max_decoded_bytes,
+            nb_bytes,
+            ResourceLimit::DecodedBytes,
+        )
+    }
+
+    /// Check that `nb_decoded` pixels can be decompressed from `nb_compressed`
+    /// bytes of image data.
+    pub(crate) fn check_lzw_ratio(&self, nb_decoded: usize, nb_compressed: usize) -> Result<()> {
+        let max_decoded = self
+            .max_lzw_ratio
+            .map(|ratio| ratio as u64 * nb_compressed as u64);
+        check(max_decoded, nb_decoded as u64, ResourceLimit::LzwRatio)
+    }
+
+    /// Check that an extension of `nb_bytes` bytes can be kept in memory.
+    pub(crate) fn check_extension_size(&self, nb_bytes: usize) -> Result<()> {
+        check(
+            self.max_extension_size,
+            nb_bytes,
+            ResourceLimit::ExtensionSize,
+        )
+    }
+
+    /// Check that decoding, whose duration is measured by `timer`, can go on.
+    pub(crate) fn check_duration(&self, timer: &DecodingTimer) -> Result<()> {
+        check(self.max_duration, timer.elapsed(), ResourceLimit::Duration)
+    }
+}
+
+/// Measures the time spent decoding, which only runs while the decoder is
+/// actually decoding (see `Limits::max_duration`).
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct DecodingTimer {
+    /// Time measured until the last time the timer was paused.
+    elapsed: Duration,
+
+    /// When the timer was last resumed, if it is running.
+    resumed_at: Option<Instant>,
+}
+
+impl DecodingTimer {
+    /// Start measuring time again, if not already doing so.
+    pub(crate) fn resume(&mut self) {
+        if self.resumed_at.is_none() {
+            self.resumed_at = Some(Instant::now());
+        }
+    }
+
+    /// Stop measuring time, until `resume` is called.
+    pub(crate) fn pause(&mut self) {
+        if let Some(resumed_at) = self.resumed_at.take() {
+            self.elapsed += resumed_at.elapsed();
+        }
+    }
+
+    /// Returns the time measured until now.
+    pub(crate) fn elapsed(&self) -> Duration {
+        self.elapsed + self.resumed_at.map_or(Duration::ZERO, |at| at.elapsed())
+    }
+}
+
+/// Returns a `GifParsingError::LimitExceeded` error for the given limit if
+/// `value` is over `max`, if set.
+fn check<T: PartialOrd>(max: Option<T>, value: T, limit: ResourceLimit) -> Result<()> {
+    match max {
+        Some(max) if value > max => Err(GifParsingError::LimitExceeded(limit)),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gif_decoder::GifDecoder;
+    use crate::gif_reader::SliceReader;
+    use crate::options::DecodeOptions;
+    use crate::parser::RawFrame;
+    use crate::test_utils::{build_animation, GifBuilder};
+
+    /// Decode every raw frame of `gif` with the given limits, stopping at the
+    /// first error.
+    fn decode_raw_frames(gif: &[u8], limits: Limits) -> Result<Vec<RawFrame>> {
+        let options = DecodeOptions {
+            limits,
+            ..Default::default()
+        };
+        GifDecoder::with_options(SliceReader::new(gif), options)?
+            .into_raw_frames()
+            .collect()
+    }
+
+    fn assert_exceeds(res: Result<Vec<RawFrame>>, limit: ResourceLimit) {
+        match res {
+            Err(GifParsingError::LimitExceeded(exceeded)) => assert_eq!(exceeded, limit),
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(_) => panic!("{} not exceeded", limit),
+        }
+    }
+
+    #[test]
+    fn checks_canvas_before_allocating_it() {
+        let limits = Limits {
+            max_canvas_pixels: Some(1 << 20),
+            ..Default::default()
+        };
+        // A canvas of 16GiB, never allocated
+        let gif = GifBuilder::new(u16::MAX, u16::MAX, 0).build();
+        assert_exceeds(decode_raw_frames(&gif, limits), ResourceLimit::CanvasPixels);
+
+        // Same for the rectangle of a frame
+        let gif = GifBuilder::new(2, 2, 0)
+            .image(0, 0, u16::MAX, u16::MAX, &[1])
+            .build();
+        assert_exceeds(decode_raw_frames(&gif, limits), ResourceLimit::CanvasPixels);
+    }
+
+    #[test]
+    fn checks_decoded_bytes_before_decoding_frame() {
+        // Frames of 4, 1, 1 and 4 pixels
+        let limits = Limits {
+            max_decoded_bytes: Some(6),
+            ..Default::default()
+        };
+        assert_exceeds(
+            decode_raw_frames(&build_animation(), limits),
+            ResourceLimit::DecodedBytes,
+        );
+
+        // Image data of 4GiB, never allocated
+        let gif = GifBuilder::new(2, 2, 0)
+            .image(0, 0, u16::MAX, u16::MAX, &[1])
+            .build();
+        assert_exceeds(decode_raw_frames(&gif, limits), ResourceLimit::DecodedBytes);
+    }
+
+    #[test]
+    fn checks_lzw_ratio_while_decoding() {
+        // 16 pixels compressed in 10 bytes
+        let gif = GifBuilder::new(4, 4, 0).image(0, 0, 4, 4, &[1; 16]).build();
+        let limits = Limits {
+            max_lzw_ratio: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(decode_raw_frames(&gif, limits).unwrap().len(), 1);
+        let limits = Limits {
+            max_lzw_ratio: Some(1),
+            ..Default::default()
+        };
+        assert_exceeds(decode_raw_frames(&gif, limits), ResourceLimit::LzwRatio);
+    }
+
+    #[test]
+    fn checks_duration_of_decoding() {
+        let limits = Limits {
+            max_duration: Some(Duration::ZERO),
+            ..Default::default()
+        };
+        assert_exceeds(
+            decode_raw_frames(&build_animation(), limits),
+            ResourceLimit::Duration,
+        );
+    }
+
+    #[test]
+    fn does_not_count_time_between_calls() {
+        let gif = build_animation();
+        let options = DecodeOptions {
+            limits: Limits {
+                max_duration: Some(Duration::from_millis(50)),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut decoder = GifDecoder::with_options(SliceReader::new(&gif), options).unwrap();
+        for _ in 0..4 {
+            std::thread::sleep(Duration::from_millis(20));
+            assert!(decoder.next_frame().unwrap().is_some());
+        }
+    }
+}
@@ -1,34 +1,113 @@
-mod color;
-mod decoder;
-mod error;
 mod frames_store;
-mod gif_reader;
-mod parser;
 
 use eframe::egui;
 use egui::{ColorImage, TextureHandle, ViewportBuilder};
 use frames_store::FramesStore;
-use gif_reader::{GifRead, GifReader};
+use gif_renderer::{DecodeOptions, GifDecoder, GifEvent, GifRead, GifReader, PixelFormat};
+use std::io::Read;
 use std::sync::mpsc::{channel, Receiver};
 
 fn main() {
     let args: Vec<String> = std::env::args().collect();
     if args.len() < 2 {
-        eprintln!("Error: Missing file path in argument.");
+        eprintln!("Error: Missing file path (or \"-\" for stdin) in argument.");
         std::process::exit(1);
     }
-    let f = std::fs::File::open(&args[1]).unwrap_or_else(|err| {
+    if args[1] == "validate" {
+        if args.len() < 3 {
+            eprintln!("Error: Missing file path(s) to validate in argument.");
+            std::process::exit(1);
+        }
+        // Go through every file, even after errors
+        let nb_invalid = args[2..].iter().filter(|path| !validate(path)).count();
+        std::process::exit(if nb_invalid == 0 { 0 } else { 1 });
+    }
+    let input = open_input(&args[1]).unwrap_or_else(|err| {
         eprintln!("Error: Error while opening {}: {}", &args[1], err);
         std::process::exit(1);
     });
 
-    let rdr = GifReader::new(std::io::BufReader::new(f));
+    let rdr = GifReader::new(std::io::BufReader::new(input));
     GifRendererEframeApp::initialize(rdr).unwrap();
 }
 
-const WINDOW_TITLE: &str = "GIF Displayer (Esc key to exit)";
+/// Open the file at the given path, "-" meaning that the GIF should be read
+/// from stdin.
+fn open_input(path: &str) -> std::io::Result<Box<dyn Read + Send>> {
+    if path == "-" {
+        Ok(Box::new(std::io::stdin()))
+    } else {
+        Ok(Box::new(std::fs::File::open(path)?))
+    }
+}
+
+/// Check the GIF file at `path` for deviations from the GIF specification and
+/// print them, along with their position in the file.
+/// Returns `false` if errors were found.
+fn validate(path: &str) -> bool {
+    let input = match open_input(path) {
+        Ok(input) => input,
+        Err(err) => {
+            println!("{}: error: cannot open it: {}", path, err);
+            return false;
+        }
+    };
+    let options = DecodeOptions {
+        // Frames are only decoded, never composited: the canvas, allocated
+        // anyway, may as well take a single byte per pixel
+        pixel_format: PixelFormat::Indexed,
+        // Report as much as possible instead of stopping at the first error
+        lenient: true,
+        strict: true,
+        ..Default::default()
+    };
+    let rdr = GifReader::new(std::io::BufReader::new(input));
+    let mut frames = match GifDecoder::with_options(rdr, options) {
+        Ok(decoder) => decoder.into_raw_frames(),
+        Err(err) => {
+            println!("{}: error: {}", path, err);
+            return false;
+        }
+    };
+
+    let mut nb_frames = 0;
+    let mut failure = None;
+    for frame in &mut frames {
+        match frame {
+            Ok(_) => nb_frames += 1,
+            Err(err) => failure = Some(err),
+        }
+    }
+    let decoder = frames.decoder();
+    let mut nb_errors = 0;
+    let mut nb_warnings = 0;
+    for warning in decoder.warnings() {
+        let severity = if warning.is_recovered_error() {
+            nb_errors += 1;
+            "error"
+        } else {
+            nb_warnings += 1;
+            "warning"
+        };
+        println!("{}: {}: {}", path, severity, warning);
+    }
+    if let Some(err) = failure {
+        nb_errors += 1;
+        println!(
+            "{}: error: {} (decoding stopped at position {})",
+            path,
+            err,
+            decoder.position()
+        );
+    }
+    println!(
+        "{}: {} frame(s), {} error(s), {} warning(s)",
+        path, nb_frames, nb_errors, nb_warnings
+    );
+    nb_errors == 0
+}
 
-use parser::GifEvent;
+const WINDOW_TITLE: &str = "GIF Displayer (Esc key to exit)";
 
 pub(crate) struct GifRendererEframeApp {
     frames: FramesStore<ColorImage>,
@@ -36,18 +115,38 @@ pub(crate) struct GifRendererEframeApp {
 
     width: usize,
     height: usize,
+    /// Size at which frames are drawn, so that non-square pixels are not
+    /// displayed stretched.
+    display_size: egui::Vec2,
+    comments: Vec<String>,
     receiver: Receiver<GifEvent>,
 }
 
 impl GifRendererEframeApp {
-    pub(crate) fn initialize(mut rdr: impl GifRead + Send + 'static) -> Result<(), eframe::Error> {
-        let header = parser::parse_header(&mut rdr).unwrap_or_else(|err| {
+    pub(crate) fn initialize(rdr: impl GifRead + Send + 'static) -> Result<(), eframe::Error> {
+        let options = DecodeOptions {
+            pixel_format: PixelFormat::Rgba8,
+            apply_color_profile: true,
+            // Display damaged GIFs as far as possible, like web browsers do
+            lenient: true,
+            threads: std::thread::available_parallelism().map_or(0, |n| n.get()),
+            ..Default::default()
+        };
+        let decoder = GifDecoder::with_options(rdr, options).unwrap_or_else(|err| {
             eprintln!("Error while parsing the GIF header: {}", err);
             std::process::exit(1);
         });
+        let mut display_size = egui::vec2(decoder.width() as f32, decoder.height() as f32);
+        match decoder.header().pixel_aspect_ratio() {
+            // Pixels wider than tall: stretch horizontally
+            Some(ratio) if ratio >= 1. => display_size.x *= ratio,
+            // Pixels taller than wide: stretch vertically
+            Some(ratio) => display_size.y /= ratio,
+            None => {}
+        }
         let viewport = ViewportBuilder::default()
             .with_title(WINDOW_TITLE)
-            .with_inner_size((header.width as f32, header.height as f32));
+            .with_inner_size(display_size);
 
         let options = eframe::NativeOptions {
             viewport,
@@ -56,19 +155,21 @@ impl GifRendererEframeApp {
             ..Default::default()
         };
 
-        let width = header.width as usize;
-        let height = header.height as usize;
+        let width = decoder.width() as usize;
+        let height = decoder.height() as usize;
         let (tx, rx) = channel::<GifEvent>();
         let app = Self {
             frames: FramesStore::new(),
             texture: None,
             width,
             height,
+            display_size,
+            comments: vec![],
             receiver: rx,
         };
         // 4 - decode GIF in another thread
         std::thread::spawn(move || {
-            if let Err(x) = parser::decode(&mut rdr, &header, tx) {
+            if let Err(x) = decoder.decode(tx) {
                 eprintln!("Error while decoding: {}", x);
                 std::process::exit(1);
             }
@@ -103,23 +204,19 @@ impl eframe::App for GifRendererEframeApp {
 
         while let Ok(event) = self.receiver.try_recv() {
             match event {
-                GifEvent::Frame { data, duration } => {
-                    // I used [u32] initially, but egui wants [u8].
-                    // I could be transmuting and stuff for max efficiency, but I'm in the middle
-                    // of changing the gui so I'm focusing on other things here
-                    let mut data_u8 = Vec::with_capacity(data.len() * std::mem::size_of::<u32>());
-                    for num in data {
-                        data_u8.extend_from_slice(&num.to_ne_bytes()); // Slice is fine here
-                    }
+                GifEvent::Frame(frame) => {
+                    // Frames are decoded in the RGBA8 format, which is what egui expects
                     let img = egui::ColorImage::from_rgba_unmultiplied(
-                        [self.width, self.height],
-                        &data_u8,
+                        [frame.width, frame.height],
+                        &frame.data,
                     );
-                    self.frames.add_frame(img, duration);
+                    self.frames.add_frame(img, frame.delay);
                 }
                 GifEvent::LoopingInfo(looping_info) => {
                     self.frames.set_loop_iterations(looping_info)
                 }
+                GifEvent::Comment(comment) => self.comments.push(comment.text),
+                GifEvent::Warning(warning) => eprintln!("Warning: {}", warning),
                 GifEvent::FrameEnd => self.frames.end_of_frames(),
             }
         }
@@ -141,13 +238,20 @@ impl eframe::App for GifRendererEframeApp {
                     ui.label("Press ESC to exit");
                     ui.separator();
                     ui.label(format!("Size: {}x{}", self.width, self.height));
+                    if !self.comments.is_empty() {
+                        ui.separator();
+                        // Comments may be long, only show them on hover
+                        ui.label(format!("Comments: {} (hover)", self.comments.len()))
+                            .on_hover_text(self.comments.join("\n\n"));
+                    }
                     // TODO: next and prev buttons?
                     ui.add_space(5.0); // right padding
                 });
                 ui.add_space(3.0); // bottom padding
 
                 if let Some(texture) = &self.texture {
-                    ui.image(texture);
+                    let texture = egui::load::SizedTexture::new(texture, self.display_size);
+                    ui.add(egui::Image::new(texture).fit_to_exact_size(self.display_size));
                 }
             });
     }
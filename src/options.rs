@@ -0,0 +1,81 @@
+use crate::color::PixelFormat;
+use crate::limits::Limits;
+
+/// Options influencing how a `GifDecoder` renders frames.
+#[derive(Debug, Clone, Default)]
+pub struct DecodeOptions {
+    /// How transparent pixels are rendered.
+    pub transparency: TransparencyMode,
+
+    /// Layout of the pixels in decoded frames.
+    pub pixel_format: PixelFormat,
+
+    /// If `true` and the GIF embeds an ICC color profile (in an
+    /// `"ICCRGBG1012"` Application Extension), colors are converted from that
+    /// profile to sRGB before being rendered.
+    /// Only RGB "matrix/TRC" profiles are supported, other ones are ignored.
+    pub apply_color_profile: bool,
+
+    /// If `true` and the GIF declares pixels which are not square (see
+    /// `GifHeader::pixel_aspect_ratio`), frames are resampled so that their
+    /// pixels are square, and thus not displayed stretched.
+    /// Frames are then enlarged, horizontally if pixels are wider than tall and
+    /// vertically otherwise, by repeating pixels (nearest neighbor).
+    pub square_pixels: bool,
+
+    /// If `true`, damaged GIFs are decoded as far as possible, like web
+    /// browsers do, instead of failing:
+    ///
+    ///   - a GIF buffer ending before its Trailer is considered to end there,
+    ///     the frame being decoded at that point being kept as is.
+    ///
+    ///   - invalid image data stops the decoding of its frame, the pixels
+    ///     decoded until then being kept.
+    ///
+    ///   - unknown extensions are skipped and malformed ones are ignored.
+    ///
+    ///   - bytes which do not start any known block are skipped.
+    ///
+    /// What was recovered from is reported as `Warning`s.
+    pub lenient: bool,
+
+    /// If `true`, the GIF buffer is also checked for deviations from the
+    /// specification which are harmless to decoding (reserved bits set, color
+    /// tables marked as sorted while they are not, frames going past the
+    /// logical screen...), reported as `Warning`s.
+    /// This is mostly useful to validate GIF files, as it makes decoding a
+    /// bit slower.
+    pub strict: bool,
+
+    /// Limits on the resources decoding may use, none by default. Decoding
+    /// fails with a `GifParsingError::LimitExceeded` error once one of them is
+    /// exceeded.
+    pub limits: Limits,
+
+    /// Number of threads decompressing the image data of frames in
+    /// `GifDecoder::decode`, while the calling thread reads the GIF buffer and
+    /// composites the decompressed frames in order. Frames (and other events)
+    /// are the same as when decoding on a single thread. The image data of each
+    /// frame is then copied to be handed to a worker thread, even when read
+    /// through a `SliceReader`.
+    /// `0`, the default, decodes everything on the calling thread.
+    pub threads: usize,
+}
+
+/// How transparent pixels - and pixels never painted by any frame - are
+/// rendered in the output canvas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TransparencyMode {
+    /// Those pixels are set to the background color defined in the GIF's
+    /// header (or to opaque white if none), so that the output is always fully
+    /// opaque.
+    #[default]
+    Flatten,
+
+    /// Those pixels are kept fully transparent (with an alpha of `0`), which is
+    /// what you want when rendering the GIF on top of something else.
+    /// Only applies to `PixelFormat`s with an alpha channel.
+    /// The `RestoreToBackgroundColor` disposal method then also restores
+    /// transparent pixels, as web browsers do.
+    KeepAlpha,
+}
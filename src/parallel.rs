@@ -0,0 +1,331 @@
+use crate::compositor::Compositor;
+use crate::error::{GifParsingError, Result};
+use crate::gif_decoder::EventSender;
+use crate::gif_reader::GifRead;
+use crate::parser::{
+    Comment, FrameParser, GifHeader, ImageData, NextFrame, PartialFrame, RawFrame,
+};
+use crate::streaming::update_background_color;
+use crate::warning::Warning;
+use std::collections::VecDeque;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+
+/// Decode every remaining frame of the GIF buffer, like `GifDecoder::decode`,
+/// decompressing their image data on `nb_threads` worker threads.
+///
+/// The calling thread goes through the blocks of the GIF buffer, reading the
+/// image data of each frame without decoding it, and hands it to the workers.
+/// Their decompressed frames are then composited, and sent along with what was
+/// encountered before them, in the order of the GIF buffer: events are the
+/// same as when decoding sequentially.
+pub(crate) fn decode(
+    rdr: &mut impl GifRead,
+    header: &GifHeader,
+    frame_parser: &mut FrameParser,
+    compositor: &mut Compositor,
+    nb_threads: usize,
+    events: &mut EventSender,
+) -> Result<()> {
+    let (job_tx, job_rx) = channel();
+    let job_rx = Mutex::new(job_rx);
+    std::thread::scope(|scope| {
+        for _ in 0..nb_threads {
+            scope.spawn(|| run_worker(&job_rx));
+        }
+        let mut pipeline = Pipeline {
+            // Reported before the first frame
+            header_warnings: frame_parser.warnings().to_vec(),
+            rdr,
+            header,
+            frame_parser,
+            compositor,
+            jobs: job_tx,
+        };
+        // Workers stop once the jobs left have been done
+        pipeline.run(nb_threads, events)
+    })
+}
+
+/// State of a parallel decoding, on the calling thread.
+struct Pipeline<'a, R: GifRead> {
+    rdr: &'a mut R,
+    header: &'a GifHeader,
+    frame_parser: &'a mut FrameParser,
+    compositor: &'a mut Compositor,
+    /// Sends image data to decompress to the worker threads.
+    jobs: Sender<Job>,
+    /// Warnings encountered on the header, until they are sent.
+    header_warnings: Vec<Warning>,
+}
+
+impl<R: GifRead> Pipeline<'_, R> {
+    /// Go through the GIF buffer, keeping up to two frames per worker thread
+    /// read ahead of the one being composited, and send the events.
+    fn run(&mut self, nb_threads: usize, events: &mut EventSender) -> Result<()> {
+        // The sort order of the Global Color Table is checked when the Trailer
+        // is reached, from the frames finished until then
+        let max_steps = if self.frame_parser.counts_global_color_uses() {
+            1
+        } else {
+            2 * nb_threads
+        };
+        let mut steps = VecDeque::with_capacity(max_steps);
+        loop {
+            while steps.len() < max_steps {
+                let step = self.read_step();
+                let is_last = matches!(step.kind, StepKind::End | StepKind::Failed(_));
+                steps.push_back(step);
+                if is_last {
+                    break;
+                }
+            }
+            let step = match steps.pop_front() {
+                Some(step) => step,
+                None => unreachable!("the last step ends the decoding"),
+            };
+            let Step {
+                comments,
+                mut warnings,
+                loop_count,
+                converts_colors,
+                kind,
+            } = step;
+            let raw_frame = match kind {
+                StepKind::Image {
+                    decoded,
+                    warnings_after,
+                } => {
+                    let DecodedFrame {
+                        mut frame,
+                        mut indices,
+                        warnings: data_warnings,
+                        result,
+                    } = decoded.recv().expect("worker threads do not panic");
+                    result?;
+                    warnings.extend(data_warnings);
+                    warnings.extend(warnings_after);
+                    let nb_warnings = self.frame_parser.warnings().len();
+                    self.frame_parser.finish_frame(&mut frame, &mut indices[..]);
+                    warnings.extend_from_slice(&self.frame_parser.warnings()[nb_warnings..]);
+                    frame.into_raw_frame(indices)
+                }
+                StepKind::PlainText(raw_frame) => raw_frame,
+                StepKind::End => return events.send_end(loop_count, &comments, &warnings),
+                StepKind::Failed(err) => return Err(err),
+            };
+            if converts_colors {
+                update_background_color(self.compositor, self.frame_parser, self.header);
+            }
+            let frame = self.compositor.render_frame(raw_frame);
+            events.send_frame(loop_count, &comments, &warnings, frame)?;
+        }
+    }
+
+    /// Go through the next blocks of the GIF buffer until the end of the next
+    /// frame, handing its image data to the worker threads.
+    fn read_step(&mut self) -> Step {
+        let nb_comments = self.frame_parser.comments().len();
+        let nb_warnings = self.frame_parser.warnings().len();
+        let next_frame = self.frame_parser.start_next_frame(self.rdr, self.header);
+        let nb_warnings_before_data = self.frame_parser.warnings().len();
+        let kind = match next_frame {
+            Ok(Some(NextFrame::Image(frame))) => {
+                let image_data = self.frame_parser.collect_image_data(self.rdr, &frame);
+                let (result_tx, result_rx) = channel();
+                let job = Job {
+                    frame: *frame,
+                    image_data,
+                    parser: self.frame_parser.derived_parser(),
+                    result: result_tx,
+                };
+                self.jobs
+                    .send(job)
+                    .expect("worker threads live as long as the pipeline");
+                StepKind::Image {
+                    decoded: result_rx,
+                    warnings_after: self.frame_parser.warnings()[nb_warnings_before_data..]
+                        .to_vec(),
+                }
+            }
+            Ok(Some(NextFrame::PlainText(raw_frame))) => StepKind::PlainText(raw_frame),
+            Ok(None) => StepKind::End,
+            Err(err) => StepKind::Failed(err),
+        };
+        let mut warnings = std::mem::take(&mut self.header_warnings);
+        warnings
+            .extend_from_slice(&self.frame_parser.warnings()[nb_warnings..nb_warnings_before_data]);
+        Step {
+            comments: self.frame_parser.comments()[nb_comments..].to_vec(),
+            warnings,
+            loop_count: self.frame_parser.loop_count(),
+            converts_colors: self.frame_parser.color_transform().is_some(),
+            kind,
+        }
+    }
+}
+
+/// What was encountered while going through the GIF buffer up to the end of a
+/// frame (or of the GIF buffer), to handle in order.
+struct Step {
+    /// Comments encountered before the frame.
+    comments: Vec<Comment>,
+
+    /// Warnings encountered before the frame's image data.
+    warnings: Vec<Warning>,
+
+    /// Looping information encountered until the frame.
+    loop_count: Option<u16>,
+
+    /// If `true`, colors were converted from an ICC color profile from that
+    /// frame on, the background color included.
+    converts_colors: bool,
+
+    kind: StepKind,
+}
+
+/// How a `Step` ended.
+enum StepKind {
+    /// A frame from an Image Descriptor, being decompressed by a worker
+    /// thread.
+    Image {
+        /// Receives the frame, once decompressed.
+        decoded: Receiver<DecodedFrame>,
+
+        /// Warnings encountered while reading the frame's image data.
+        warnings_after: Vec<Warning>,
+    },
+
+    /// A frame from a Plain Text Extension, already rendered.
+    PlainText(RawFrame),
+
+    /// The Trailer was reached.
+    End,
+
+    /// Decoding failed with that error.
+    Failed(GifParsingError),
+}
+
+/// Image data of a frame, to decompress on a worker thread.
+struct Job {
+    frame: PartialFrame,
+    image_data: ImageData,
+    /// Decodes the image data like the calling thread would.
+    parser: FrameParser,
+    /// Sends back the decompressed frame.
+    result: Sender<DecodedFrame>,
+}
+
+/// A frame decompressed by a worker thread.
+struct DecodedFrame {
+    frame: PartialFrame,
+    /// Indices of the frame's pixels, row by row. See `RawFrame::indices`.
+    indices: Vec<u8>,
+    /// Warnings encountered while decompressing the image data.
+    warnings: Vec<Warning>,
+    /// Error which stopped the decompression, if one.
+    result: Result<()>,
+}
+
+/// Decompress the image data of the jobs received, until the pipeline stops.
+fn run_worker(jobs: &Mutex<Receiver<Job>>) {
+    loop {
+        // Only hold the lock while waiting for a job
+        let job = jobs.lock().unwrap().recv();
+        let Job {
+            mut frame,
+            image_data,
+            mut parser,
+            result,
+        } = match job {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+        let mut indices = vec![0; frame.nb_pixels()];
+        let res = parser.decode_collected_image_data(&mut frame, image_data, &mut indices[..]);
+        // The pipeline may have stopped on an error in the meantime
+        let _ = result.send(DecodedFrame {
+            frame,
+            indices,
+            warnings: parser.warnings().to_vec(),
+            result: res,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gif_decoder::GifDecoder;
+    use crate::gif_reader::SliceReader;
+    use crate::options::DecodeOptions;
+    use crate::test_utils::{build_animation, GifBuilder};
+    use std::sync::mpsc::channel;
+
+    /// Decode `gif` through `GifDecoder::decode` and return the events sent
+    /// and its outcome, formatted so that they can be compared.
+    fn decode_events(gif: &[u8], options: DecodeOptions) -> (Vec<String>, Result<(), String>) {
+        let decoder = GifDecoder::with_options(SliceReader::new(gif), options).unwrap();
+        let (tx, rx) = channel();
+        let res = decoder.decode(tx).map_err(|err| err.to_string());
+        (
+            rx.try_iter().map(|event| format!("{:?}", event)).collect(),
+            res,
+        )
+    }
+
+    /// Check that decoding `gif` on several threads sends the same events as
+    /// decoding it sequentially.
+    fn assert_same_as_sequential(gif: &[u8], options: DecodeOptions) {
+        let expected = decode_events(gif, options.clone());
+        for threads in &[1, 2, 4] {
+            let options = DecodeOptions {
+                threads: *threads,
+                ..options.clone()
+            };
+            assert_eq!(decode_events(gif, options), expected, "{} threads", threads);
+        }
+    }
+
+    #[test]
+    fn decodes_like_sequential_decoding() {
+        let gif = build_animation();
+        assert_same_as_sequential(&gif, DecodeOptions::default());
+        let (events, res) = decode_events(&gif, DecodeOptions::default());
+        assert!(res.is_ok());
+        assert_eq!(events.iter().filter(|e| e.starts_with("Frame(")).count(), 4);
+    }
+
+    #[test]
+    fn reports_warnings_like_sequential_decoding() {
+        // Invalid background color index, truncated second frame
+        let indices: Vec<u8> = (0..64).map(|i| i % 4).collect();
+        let gif = GifBuilder::new(8, 8, 9)
+            .image(0, 0, 8, 8, &indices)
+            .image(0, 0, 8, 8, &indices)
+            .build();
+        let gif = &gif[..gif.len() - 30];
+        let options = DecodeOptions {
+            lenient: true,
+            strict: true,
+            ..Default::default()
+        };
+        assert_same_as_sequential(gif, options.clone());
+        let (events, _) = decode_events(gif, options);
+        assert!(events
+            .iter()
+            .any(|e| e.starts_with("Warning(UnexpectedEnd")));
+        assert!(events
+            .iter()
+            .any(|e| e.starts_with("Warning(InvalidBackgroundColorIndex")));
+    }
+
+    #[test]
+    fn fails_like_sequential_decoding() {
+        let mut gif = build_animation();
+        // Replace the Trailer by an unknown block
+        *gif.last_mut().unwrap() = 0x42;
+        assert_same_as_sequential(&gif, DecodeOptions::default());
+        let (_, res) = decode_events(&gif, DecodeOptions::default());
+        assert!(res.is_err());
+    }
+}
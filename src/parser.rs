@@ -1,16 +1,26 @@
+use crate::application_extension::{
+    self, ApplicationExtension, ApplicationExtensionRegistry, ANIMEXTS_IDENTIFIER, ICC_IDENTIFIER,
+    NETSCAPE_IDENTIFIER, XMP_IDENTIFIER,
+};
 use crate::color::{self, Rgb};
-use crate::decoder::LzwDecoder;
+use crate::decoder::{LzwDecoder, LzwError};
 use crate::error::{GifParsingError, Result};
+use crate::gif_decoder::GifDecoder;
 use crate::gif_reader::{GifRead, GifReaderStringError};
+use crate::icc::IccTransform;
+use crate::limits::{DecodingTimer, Limits};
+use crate::options::DecodeOptions;
+use crate::plain_text::{self, PlainText};
+use crate::warning::Warning;
 
 /// GIF block ID for the "Image Descriptor".
-const IMAGE_DESCRIPTOR_BLOCK_ID: u8 = 0x2C;
+pub(crate) const IMAGE_DESCRIPTOR_BLOCK_ID: u8 = 0x2C;
 
 /// GIF block ID for the "Trailer".
-const TRAILER_BLOCK_ID: u8 = 0x3B;
+pub(crate) const TRAILER_BLOCK_ID: u8 = 0x3B;
 
 /// GIF block ID for the "Extension Introducer".
-const EXTENSION_INTRODUCER_ID: u8 = 0x21;
+pub(crate) const EXTENSION_INTRODUCER_ID: u8 = 0x21;
 
 /// GIF block ID for the "Graphic Control Extension".
 const GRAPHIC_CONTROL_EXTENSION_LABEL: u8 = 0xF9;
@@ -24,187 +34,1566 @@ const COMMENT_EXTENSION_LABEL: u8 = 0xFE;
 /// GIF block ID for a "Plain Text Extension".
 const PLAIN_TEXT_EXTENSION_LABEL: u8 = 0x01;
 
-/// Background color used when none is defined.
-const DEFAULT_BACKGROUND_COLOR: u32 = 0xFFFF_FFFF;
+/// A single decoded frame of a GIF image.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    /// Pixels of the whole logical screen once that frame has been rendered,
+    /// row by row, in the `PixelFormat` asked for.
+    /// Its dimensions are given by `width` and `height`.
+    /// Depending on the `TransparencyMode` used, transparent pixels either have
+    /// the background color or an alpha of `0`.
+    pub data: Vec<u8>,
 
-pub(crate) enum GifEvent {
-    Frame {
-        data: Vec<u32>,
-        duration: Option<u16>,
-    },
+    /// Width, in pixels, of the image in `data`.
+    /// This is the width of the logical screen, unless frames are resampled to
+    /// square pixels (see `DecodeOptions::square_pixels`).
+    pub width: usize,
 
+    /// Height, in pixels, of the image in `data`.
+    /// This is the height of the logical screen, unless frames are resampled
+    /// to square pixels (see `DecodeOptions::square_pixels`).
+    pub height: usize,
+
+    /// Color table used by that frame, only communicated when the
+    /// `PixelFormat::Indexed` format is asked for.
+    ///
+    /// Note that pixels left from previous frames are also written as indices:
+    /// if those used another (local) color table, their index may not
+    /// correspond to the right color in this one.
+    pub palette: Option<Vec<Rgb>>,
+
+    /// Time, in hundredths (1/100) of a second, during which that frame should
+    /// be displayed.
+    /// `None` if no delay was specified for that frame.
+    pub delay: Option<u16>,
+
+    /// Information on how that frame was built.
+    pub info: FrameInfo,
+}
+
+/// A frame as it is stored in the GIF buffer, before any compositing.
+///
+/// Only the frame's own rectangle is present, as indices into its color table.
+/// The disposal method of the previous frames and the transparency are not
+/// applied: this is the job of the compositing step.
+#[derive(Debug, Clone)]
+pub struct RawFrame {
+    /// Indices in `palette` of each pixel of the frame's rectangle, row by row
+    /// (already de-interlaced if the frame was interlaced).
+    /// Its length is always `info.width * info.height`.
+    ///
+    /// Pixels missing from the compressed data (see `nb_decoded`) are set to
+    /// `0`.
+    pub indices: Vec<u8>,
+
+    /// Number of pixels decoded from the compressed data, in the order in
+    /// which they are stored in it (see `FrameInfo::interlaced`): the others
+    /// are missing, and are left untouched when compositing the frame.
+    /// Equal to the length of `indices` unless the frame is incomplete (see
+    /// `Warning::IncompleteFrame`).
+    pub nb_decoded: usize,
+
+    /// Color table used by that frame: its Local Color Table if it had one,
+    /// the Global Color Table if not.
+    pub palette: Vec<Rgb>,
+
+    /// Time, in hundredths (1/100) of a second, during which that frame should
+    /// be displayed.
+    /// `None` if no delay was specified for that frame.
+    pub delay: Option<u16>,
+
+    /// Information on how that frame was built.
+    pub info: FrameInfo,
+}
+
+/// Metadata on a frame, as parsed from its Image Descriptor (or Plain Text
+/// Extension) and from the Graphic Control Extension preceding it, if one.
+#[derive(Debug, Clone)]
+pub struct FrameInfo {
+    /// Position in the GIF buffer of the frame's Image Descriptor, or of the
+    /// Extension Introducer of its Plain Text Extension.
+    pub position: usize,
+
+    /// Column, in pixels, of the left edge of the frame in the logical screen.
+    pub left: u16,
+
+    /// Row, in pixels, of the top edge of the frame in the logical screen.
+    pub top: u16,
+
+    /// Width of the frame, in pixels.
+    pub width: u16,
+
+    /// Height of the frame, in pixels.
+    pub height: u16,
+
+    /// The way in which the frame is to be treated after being displayed.
+    pub disposal_method: DisposalMethod,
+
+    /// Index in the frame's color table of the color considered transparent,
+    /// if one.
+    pub transparent_color_index: Option<u8>,
+
+    /// If `true`, the GIF asks for the animation to continue once user input
+    /// is entered (or once the delay expires, whichever occurs first).
+    pub user_input: bool,
+
+    /// If `true`, the frame's rows were stored in the interlaced order.
+    pub interlaced: bool,
+
+    /// If `true`, the frame had its own Local Color Table. If `false`, it used
+    /// the Global Color Table.
+    pub has_local_color_table: bool,
+
+    /// If the frame comes from a Plain Text Extension instead of an Image
+    /// Descriptor, the text it renders (with the built-in font) and how.
+    pub plain_text: Option<PlainText>,
+}
+
+/// Events sent by `decode` while it is going through a GIF buffer.
+#[derive(Debug)]
+pub enum GifEvent {
+    /// A new frame has been decoded.
+    Frame(Frame),
+
+    /// Looping information has been found (or its absence has been detected).
+    /// `None` means that the GIF should not loop, `Some(0)` that it should loop
+    /// infinitely and any other value is the number of time it should loop.
     LoopingInfo(Option<u16>),
 
+    /// A Comment Extension has been encountered.
+    Comment(Comment),
+
+    /// A deviation from the GIF specification has been encountered, which did
+    /// not prevent decoding from going on.
+    /// Warnings are sent before the frame during which they were encountered.
+    Warning(Warning),
+
+    /// All frames have been decoded.
     FrameEnd,
 }
 
+/// Text found in a Comment Extension of a GIF buffer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Comment {
+    /// Position in the GIF buffer of the Comment Extension.
+    pub position: usize,
+
+    /// Number of frames coming before the comment in the GIF buffer, which is
+    /// also the index of the frame following it.
+    pub nb_preceding_frames: usize,
+
+    /// Text of the comment. Bytes which are not valid UTF-8 (the GIF
+    /// specification only mentions 7-bit ASCII) are replaced by `U+FFFD`.
+    pub text: String,
+}
+
+/// Decode every frame of the GIF buffer, which should be positioned just after
+/// its header (see `parse_header`), and send them as they are decoded through
+/// `tx`.
+///
+/// This is a simple adapter over a `GifDecoder`, for when you prefer to be
+/// pushed new frames from another thread rather than pulling them yourself.
 pub fn decode(
     rdr: &mut impl GifRead,
     header: &GifHeader,
     tx: std::sync::mpsc::Sender<GifEvent>,
 ) -> Result<()> {
-    let (background_color, global_color_table) = if let Some(gct) = &header.global_color_table {
-        let index = header.background_color_index as usize;
-        if gct.len() <= index {
-            // TODO log "Invalid background color index" warning?
-            (None, Some(gct.as_slice()))
+    GifDecoder::from_parts(rdr, header.clone(), DecodeOptions::default())?.decode(tx)
+}
+
+/// State kept in between frames while going through the blocks of a GIF
+/// buffer, so frames can be decoded one at a time.
+pub(crate) struct FrameParser {
+    /// Last graphic extension encountered. Will be needed when an Image
+    /// Descriptor is encountered.
+    last_graphic_ext: Option<GraphicControlExtension>,
+
+    /// Looping value found in a NETSCAPE2.0 (or ANIMEXTS1.0) Application
+    /// Extension, if one was encountered yet.
+    loop_count: Option<u16>,
+
+    /// Buffer size found in a NETSCAPE2.0 (or ANIMEXTS1.0) Application
+    /// Extension, if one was encountered yet.
+    buffer_size: Option<u32>,
+
+    /// XMP packet found in an Application Extension, if one was encountered
+    /// yet.
+    xmp_packet: Option<Vec<u8>>,
+
+    /// ICC color profile found in an Application Extension, if one was
+    /// encountered yet.
+    icc_profile: Option<Vec<u8>>,
+
+    /// Handlers registered by the user for Application Extensions.
+    application_handlers: ApplicationExtensionRegistry,
+
+    /// If `true`, colors are converted to sRGB once an ICC color profile is
+    /// encountered.
+    apply_color_profile: bool,
+
+    /// Conversion of the colors to sRGB, once a supported ICC color profile
+    /// has been encountered, if `apply_color_profile` is set.
+    color_transform: Option<IccTransform>,
+
+    /// Set to `true` once the Trailer has been encountered.
+    ended: bool,
+
+    /// Kinds of extension encountered until now, in the order in which they
+    /// were first encountered.
+    extensions: Vec<ExtensionKind>,
+
+    /// Comments encountered until now, in order.
+    comments: Vec<Comment>,
+
+    /// Number of Image Descriptors encountered until now.
+    nb_frames: usize,
+
+    /// If `true`, errors which can be recovered from are reported as warnings
+    /// instead. See `DecodeOptions::lenient`.
+    lenient: bool,
+
+    /// Warnings encountered until now, in order.
+    warnings: Vec<Warning>,
+
+    /// If `true`, the GIF buffer is also checked for deviations from the
+    /// specification which are harmless to decoding. See
+    /// `DecodeOptions::strict`.
+    strict: bool,
+
+    /// Version declared in the header, once checked (see `check_header`).
+    version: Option<GifVersion>,
+
+    /// Dimensions of the logical screen, once the header has been checked.
+    screen_size: Option<(u16, u16)>,
+
+    /// When decoding strictly and the Global Color Table is marked as sorted:
+    /// the position of its Sort Flag, and the number of uses of each of its
+    /// colors until now.
+    global_color_uses: Option<(usize, Vec<usize>)>,
+
+    /// Position of the Application Extension which defined the looping value,
+    /// if one.
+    loop_position: Option<usize>,
+
+    /// Limits on the resources decoding may use.
+    limits: Limits,
+
+    /// Measures the time spent decoding, see `Limits::max_duration`.
+    timer: DecodingTimer,
+
+    /// Number of pixels of all frames started until now.
+    nb_decoded_bytes: u64,
+}
+
+impl FrameParser {
+    /// Create a new `FrameParser`, for a reader positioned just after the GIF
+    /// header.
+    pub(crate) fn new() -> FrameParser {
+        FrameParser {
+            last_graphic_ext: None,
+            loop_count: None,
+            buffer_size: None,
+            xmp_packet: None,
+            icc_profile: None,
+            application_handlers: ApplicationExtensionRegistry::default(),
+            apply_color_profile: false,
+            color_transform: None,
+            ended: false,
+            extensions: vec![],
+            comments: vec![],
+            nb_frames: 0,
+            lenient: false,
+            warnings: vec![],
+            strict: false,
+            version: None,
+            screen_size: None,
+            global_color_uses: None,
+            loop_position: None,
+            limits: Limits::default(),
+            timer: DecodingTimer::default(),
+            nb_decoded_bytes: 0,
+        }
+    }
+
+    /// Looping value encountered until now.
+    /// 0 means infinite looping, any other value would be the number of time
+    /// the GIF image needs to be looped.
+    pub(crate) fn loop_count(&self) -> Option<u16> {
+        self.loop_count
+    }
+
+    /// Size, in bytes, of the buffer the GIF asks to fill before playback,
+    /// as found in a NETSCAPE2.0 Application Extension.
+    pub(crate) fn buffer_size(&self) -> Option<u32> {
+        self.buffer_size
+    }
+
+    /// XMP packet encountered until now, if one.
+    pub(crate) fn xmp_packet(&self) -> Option<&[u8]> {
+        self.xmp_packet.as_deref()
+    }
+
+    /// ICC color profile encountered until now, if one.
+    pub(crate) fn icc_profile(&self) -> Option<&[u8]> {
+        self.icc_profile.as_deref()
+    }
+
+    /// If `true`, colors of the frames are converted to sRGB once an ICC
+    /// color profile has been encountered.
+    pub(crate) fn set_apply_color_profile(&mut self, apply_color_profile: bool) {
+        self.apply_color_profile = apply_color_profile;
+    }
+
+    /// Conversion of the colors to sRGB, once a supported ICC color profile
+    /// has been encountered, if colors have to be converted.
+    pub(crate) fn color_transform(&self) -> Option<&IccTransform> {
+        self.color_transform.as_ref()
+    }
+
+    /// Set the ICC color profile, if none was encountered yet, for example
+    /// because it was found while going through the GIF buffer separately.
+    pub(crate) fn set_icc_profile_if_none(&mut self, profile: &[u8]) {
+        if self.icc_profile.is_none() {
+            self.set_icc_profile(profile.to_vec());
+        }
+    }
+
+    /// Store the given ICC color profile, and prepare the conversion of the
+    /// colors if asked to.
+    fn set_icc_profile(&mut self, profile: Vec<u8>) {
+        if self.apply_color_profile {
+            self.color_transform = IccTransform::parse(&profile);
+        }
+        self.icc_profile = Some(profile);
+    }
+
+    /// Register a handler called with every Application Extension whose
+    /// identifier (authentication code included) is `identifier`.
+    pub(crate) fn register_application_handler(
+        &mut self,
+        identifier: &str,
+        handler: Box<dyn FnMut(&ApplicationExtension) + Send>,
+    ) {
+        self.application_handlers.register(identifier, handler);
+    }
+
+    /// Kinds of extension encountered until now, in the order in which they
+    /// were first encountered.
+    pub(crate) fn extensions(&self) -> &[ExtensionKind] {
+        &self.extensions
+    }
+
+    /// Comments encountered until now, in order.
+    pub(crate) fn comments(&self) -> &[Comment] {
+        &self.comments
+    }
+
+    /// If `true`, errors which can be recovered from are reported as warnings
+    /// instead. See `DecodeOptions::lenient`.
+    pub(crate) fn set_lenient(&mut self, lenient: bool) {
+        self.lenient = lenient;
+    }
+
+    /// Returns `true` if errors which can be recovered from are reported as
+    /// warnings instead.
+    pub(crate) fn is_lenient(&self) -> bool {
+        self.lenient
+    }
+
+    /// Warnings encountered until now, in order.
+    pub(crate) fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// If `true`, the GIF buffer is also checked for deviations from the
+    /// specification which are harmless to decoding. See
+    /// `DecodeOptions::strict`.
+    pub(crate) fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Set the limits on the resources decoding may use. See
+    /// `DecodeOptions::limits`.
+    pub(crate) fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// Create a new `FrameParser` decoding the same GIF buffer the way this one
+    /// would: with the same leniency and limits, adding to the decoding
+    /// duration measured until now.
+    ///
+    /// It is only meant to go through the GIF buffer once through
+    /// `skip_frame` (e.g. to build a `FrameIndex`), or to decode image data
+    /// through `decode_collected_image_data`.
+    pub(crate) fn derived_parser(&self) -> FrameParser {
+        FrameParser {
+            lenient: self.lenient,
+            limits: self.limits,
+            timer: self.timer,
+            ..FrameParser::new()
+        }
+    }
+
+    /// Start measuring the time spent decoding again, if not already doing so.
+    /// See `Limits::max_duration`.
+    pub(crate) fn resume_timer(&mut self) {
+        self.timer.resume();
+    }
+
+    /// Stop measuring the time spent decoding, until `resume_timer` is called.
+    pub(crate) fn pause_timer(&mut self) {
+        self.timer.pause();
+    }
+
+    /// Returns `true` if the uses of the colors of the Global Color Table are
+    /// counted, to check that it is sorted: every frame then has to be
+    /// finished (see `finish_frame`) before the Trailer is reached.
+    pub(crate) fn counts_global_color_uses(&self) -> bool {
+        self.global_color_uses.is_some()
+    }
+
+    /// Check that the frame which was just counted, described by `info`, can
+    /// be decoded without exceeding the resource limits, and count its pixels
+    /// as decoded.
+    fn check_frame_limits(&mut self, info: &FrameInfo) -> Result<()> {
+        self.limits.check_frames(self.nb_frames)?;
+        self.limits.check_duration(&self.timer)?;
+        let (width, height) = (info.width as usize, info.height as usize);
+        self.limits.check_canvas(width, height)?;
+        self.nb_decoded_bytes += width as u64 * height as u64;
+        self.limits.check_decoded_bytes(self.nb_decoded_bytes)
+    }
+
+    /// Check the given header for deviations from the specification, and keep
+    /// what is needed to check the following blocks.
+    pub(crate) fn check_header(&mut self, header: &GifHeader) {
+        self.version = Some(header.version);
+        self.screen_size = Some((header.width, header.height));
+        if let Some(gct) = &header.global_color_table {
+            let packed_field_pos = header.packed_fields_position;
+            if header.background_color_index as usize >= gct.len() {
+                self.add_warning(Warning::InvalidBackgroundColorIndex {
+                    index: header.background_color_index,
+                    position: packed_field_pos + 1,
+                });
+            }
+            if self.strict && header.is_table_sorted {
+                self.global_color_uses = Some((packed_field_pos, vec![0; gct.len()]));
+            }
+        }
+    }
+
+    /// Record the given warning.
+    fn add_warning(&mut self, warning: Warning) {
+        // The same warning may be encountered again after seeking
+        if !self.warnings.contains(&warning) {
+            self.warnings.push(warning);
+        }
+    }
+
+    /// Record that `nb_bytes` bytes at `position`, which do not start any
+    /// known block, have been skipped, merging them with the bytes skipped
+    /// just before them.
+    pub(crate) fn skip_unknown_bytes(&mut self, position: usize, nb_bytes: usize) {
+        if let Some(Warning::SkippedBytes {
+            position: start,
+            length,
+        }) = self.warnings.last_mut()
+        {
+            if *start + *length == position {
+                *length += nb_bytes;
+                return;
+            }
+        }
+        self.add_warning(Warning::SkippedBytes {
+            position,
+            length: nb_bytes,
+        });
+    }
+
+    /// Indicate that the GIF buffer ended before its Trailer, in the block at
+    /// `position`, and consider that the Trailer has been reached.
+    pub(crate) fn end_unexpectedly(&mut self, position: usize) {
+        self.ended = true;
+        self.add_warning(Warning::UnexpectedEnd { position });
+    }
+
+    /// Recover from the given error, encountered while parsing the block at
+    /// `position`, if it is due to the GIF buffer ending there and if errors
+    /// have to be recovered from. Returns that error otherwise.
+    fn recover_from_end(&mut self, err: GifParsingError, position: usize) -> Result<()> {
+        if self.lenient && is_unexpected_end(&err) {
+            self.end_unexpectedly(position);
+            Ok(())
         } else {
-            (Some(gct[index]), Some(gct.as_slice()))
+            Err(err)
         }
-    } else {
-        (None, None)
-    };
+    }
 
-    // Last graphic extension encountered. Will be needed when an Image Descriptor
-    // is encountered.
-    let mut last_graphic_ext: Option<GraphicControlExtension> = None;
+    /// Go through the next blocks of the GIF buffer until the next frame is
+    /// decoded and return it, as it is stored in the GIF buffer.
+    /// Returns `None` once the Trailer has been reached.
+    pub(crate) fn next_raw_frame(
+        &mut self,
+        rdr: &mut impl GifRead,
+        header: &GifHeader,
+    ) -> Result<Option<RawFrame>> {
+        let mut frame = match self.start_next_frame(rdr, header)? {
+            Some(NextFrame::Image(frame)) => frame,
+            Some(NextFrame::PlainText(raw_frame)) => return Ok(Some(raw_frame)),
+            None => return Ok(None),
+        };
+        let mut indices = vec![0; frame.nb_pixels()];
+        self.decode_image_data(rdr, &mut frame, &mut indices[..])?;
+        self.finish_frame(&mut frame, &mut indices[..]);
+        Ok(Some(frame.into_raw_frame(indices)))
+    }
 
-    // Background for the next frame encountered. Its content depends on the
-    // "disposal method" of the next frame encountered.
-    let mut next_frame_base_buffer: Option<Vec<u32>> = None;
+    /// Go through the next blocks of the GIF buffer until the next frame is
+    /// found, and return it. Image Descriptors are returned just before their
+    /// image data, which can then be decoded through `decode_image_data`.
+    /// Returns `None` once the Trailer has been reached.
+    pub(crate) fn start_next_frame(
+        &mut self,
+        rdr: &mut impl GifRead,
+        header: &GifHeader,
+    ) -> Result<Option<NextFrame>> {
+        let position = match self.next_graphic(rdr)? {
+            Some(Graphic::Image(position)) => position,
+            Some(Graphic::PlainText(position, plain_text)) => {
+                return self
+                    .plain_text_frame(header, position, plain_text)
+                    .map(|raw_frame| Some(NextFrame::PlainText(raw_frame)));
+            }
+            None => return Ok(None),
+        };
+        match self.start_frame(rdr, header, position) {
+            Ok(frame) => Ok(Some(NextFrame::Image(Box::new(frame)))),
+            Err(err) => {
+                self.recover_from_end(err, position)?;
+                Ok(None)
+            }
+        }
+    }
 
-    let mut found_loop_attribute = false;
+    /// Read and decode the whole image data of the given frame, giving each
+    /// of its rows to `sink` as soon as it is decoded.
+    /// If errors have to be recovered from, the pixels decoded until the GIF
+    /// buffer ended are kept.
+    pub(crate) fn decode_image_data<S: RowSink + ?Sized>(
+        &mut self,
+        rdr: &mut impl GifRead,
+        frame: &mut PartialFrame,
+        sink: &mut S,
+    ) -> Result<()> {
+        if let Err(err) = self.read_image_data(rdr, frame, sink) {
+            // Keep what could be decoded
+            self.recover_from_end(err, frame.position())?;
+        }
+        Ok(())
+    }
 
-    loop {
-        match rdr.read_u8()? {
-            IMAGE_DESCRIPTOR_BLOCK_ID => {
-                let (delay, transparent_color_index) = match &last_graphic_ext {
-                    Some(e) => {
-                        if e.delay == 0 {
-                            (None, e.transparent_color_index)
-                        } else {
-                            (Some(e.delay), e.transparent_color_index)
-                        }
-                    }
-                    None => (None, None),
-                };
+    /// Read and decode the image data of the given frame, until its block
+    /// terminator.
+    fn read_image_data<S: RowSink + ?Sized>(
+        &mut self,
+        rdr: &mut impl GifRead,
+        frame: &mut PartialFrame,
+        sink: &mut S,
+    ) -> Result<()> {
+        let mut sub_block_data = [0; 255];
+        loop {
+            if frame.ignores_data() {
+                return skip_sub_blocks(rdr);
+            }
+            let sub_block_size = rdr.read_u8()? as usize;
+            if sub_block_size == 0x00
+            /* block terminator */
+            {
+                return Ok(());
+            }
+            let sub_block_pos = rdr.get_pos();
+            let sub_block_data = rdr.read_borrowed(&mut sub_block_data[..sub_block_size])?;
+            self.push_image_data(frame, sub_block_data, sub_block_pos, sink)?;
+        }
+    }
 
-                // The "RestoreToPrevious" disposal method forces us to keep the current base
-                // buffer for the frame coming after that one.
-                use DisposalMethod::*;
-                let cloned_image_background = match last_graphic_ext {
-                    Some(GraphicControlExtension {
-                        disposal_method: RestoreToPrevious,
-                        ..
-                    }) => next_frame_base_buffer.clone(),
-                    _ => None,
-                };
+    /// Read the whole image data of the given frame without decoding it, so
+    /// that it can be decoded later, possibly on another thread, through
+    /// `decode_collected_image_data`.
+    /// If errors have to be recovered from, the sub-blocks read until the GIF
+    /// buffer ended are kept.
+    pub(crate) fn collect_image_data(
+        &mut self,
+        rdr: &mut impl GifRead,
+        frame: &PartialFrame,
+    ) -> ImageData {
+        let mut image_data = ImageData {
+            data: vec![],
+            sub_blocks: vec![],
+            error: None,
+        };
+        if let Err(err) = image_data.read(rdr) {
+            // Keep what could be read
+            image_data.error = self.recover_from_end(err, frame.position()).err();
+        }
+        image_data
+    }
+
+    /// Decode the image data of the given frame, collected through
+    /// `collect_image_data`, giving each of its rows to `sink` as soon as it is
+    /// decoded. The outcome is the same as if `decode_image_data` had been
+    /// called instead of `collect_image_data`.
+    pub(crate) fn decode_collected_image_data<S: RowSink + ?Sized>(
+        &mut self,
+        frame: &mut PartialFrame,
+        image_data: ImageData,
+        sink: &mut S,
+    ) -> Result<()> {
+        let mut start = 0;
+        for &(position, size) in &image_data.sub_blocks {
+            if frame.ignores_data() {
+                break;
+            }
+            let sub_block_data = &image_data.data[start..start + size];
+            self.push_image_data(frame, sub_block_data, position, sink)?;
+            start += size;
+        }
+        match image_data.error {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
 
-                let block = construct_next_frame(
-                    rdr,
-                    &global_color_table,
-                    next_frame_base_buffer,
-                    header.height,
-                    header.width,
-                    background_color,
-                    transparent_color_index,
-                )?;
-
-                // Obtain the base buffer for the next frame according to the current disposal
-                // method
-                next_frame_base_buffer = match last_graphic_ext {
-                    Some(GraphicControlExtension {
-                        disposal_method: DoNotDispose,
-                        ..
-                    })
-                    | Some(GraphicControlExtension {
-                        disposal_method: NoDisposalSpecified,
-                        ..
-                    }) => Some(block.clone()),
-                    Some(GraphicControlExtension {
-                        disposal_method: RestoreToPrevious,
-                        ..
-                    }) => cloned_image_background,
-                    _ => None,
+    /// Decode the given part of the image data of `frame`, which was at the
+    /// position `data_pos` in the GIF buffer, giving each completed row to
+    /// `sink`.
+    /// If errors have to be recovered from, invalid image data stops the
+    /// decoding of the frame instead of returning an error.
+    /// Fails if the frame's decompressed data gets too large compared to its
+    /// compressed data, or decoding takes too long (see `Limits`).
+    pub(crate) fn push_image_data<S: RowSink + ?Sized>(
+        &mut self,
+        frame: &mut PartialFrame,
+        data: &[u8],
+        data_pos: usize,
+        sink: &mut S,
+    ) -> Result<()> {
+        match frame.push_data(data, data_pos, sink) {
+            Err(err) if self.lenient => {
+                let position = match err {
+                    GifParsingError::InvalidLzwCode { position, .. } => position,
+                    _ => data_pos,
                 };
-                tx.send(GifEvent::Frame {
-                    data: block,
-                    duration: delay,
-                })
-                .unwrap_or_else(|err| {
-                    eprintln!(
-                        "Error: Impossible to communicate a new decoded frame: {}",
-                        err
-                    );
-                    std::process::exit(1);
+                self.add_warning(Warning::InvalidImageData {
+                    frame_position: frame.info.position,
+                    position,
                 });
+                frame.stop();
+            }
+            res => res?,
+        }
+        self.limits
+            .check_lzw_ratio(frame.nb_decoded + frame.nb_excess, frame.nb_compressed)?;
+        self.limits.check_duration(&self.timer)
+    }
+
+    /// End the decoding of the given frame, reporting a warning if some of its
+    /// pixels are missing. The decoded pixels of the row those start on are
+    /// then given to `sink`, missing pixels being left untouched.
+    pub(crate) fn finish_frame<S: RowSink + ?Sized>(
+        &mut self,
+        frame: &mut PartialFrame,
+        sink: &mut S,
+    ) {
+        if frame.nb_decoded < frame.nb_pixels() {
+            self.add_warning(Warning::IncompleteFrame {
+                position: frame.info.position,
+                nb_decoded: frame.nb_decoded,
+                nb_pixels: frame.nb_pixels(),
+            });
+            frame.emit_incomplete_row(sink);
+        }
+        if frame.nb_excess > 0 {
+            self.add_warning(Warning::ExcessPixels {
+                position: frame.info.position,
+                nb_excess: frame.nb_excess,
+            });
+        }
+        if self.strict {
+            self.count_color_uses(frame);
+        }
+    }
+
+    /// Count the uses of the colors of the given frame's color table, to check
+    /// that it is sorted if it is marked as such.
+    fn count_color_uses(&mut self, frame: &PartialFrame) {
+        let frame_uses = match &frame.color_uses {
+            Some(uses) => uses,
+            None => return,
+        };
+        if frame.info.has_local_color_table {
+            if !is_sorted_by_uses(frame_uses) {
+                self.add_warning(Warning::UnsortedColorTable {
+                    position: frame.info.position + 9,
+                });
+            }
+        } else if let Some((_, uses)) = &mut self.global_color_uses {
+            for (nb_uses, frame_nb_uses) in uses.iter_mut().zip(frame_uses) {
+                *nb_uses += frame_nb_uses;
+            }
+        }
+    }
+
+    /// Go through the next blocks of the GIF buffer until the next frame is
+    /// found, and return its information without decoding its image data.
+    /// Returns `None` once the Trailer has been reached.
+    /// Fails if that frame exceeds the resource limits, like when decoding it.
+    pub(crate) fn skip_frame(&mut self, rdr: &mut impl GifRead) -> Result<Option<SkippedFrame>> {
+        let position = match self.next_graphic(rdr)? {
+            Some(Graphic::Image(position)) => position,
+            Some(Graphic::PlainText(position, plain_text)) => {
+                let (info, delay) = self.plain_text_info(position, plain_text);
+                self.check_frame_limits(&info)?;
+                return Ok(Some(SkippedFrame {
+                    info,
+                    delay,
+                    local_color_table_size: None,
+                }));
+            }
+            None => return Ok(None),
+        };
+        // Like when decoding it, the frame is only there if everything
+        // preceding its image data is
+        let frame_start = self.parse_frame_info(rdr, position).and_then(|frame_info| {
+            if frame_info.0.has_local_color_table {
+                rdr.skip_bytes(3 * frame_info.0.nb_color_entries)?;
+            }
+            rdr.skip_bytes(1)?; // LZW minimum code size
+            Ok(frame_info)
+        });
+        let (descriptor, info, delay) = match frame_start {
+            Ok(frame_info) => frame_info,
+            Err(err) => {
+                self.recover_from_end(err, position)?;
+                return Ok(None);
+            }
+        };
+        self.check_frame_limits(&info)?;
+        let local_color_table_size = if descriptor.has_local_color_table {
+            Some(descriptor.nb_color_entries)
+        } else {
+            None
+        };
+        if let Err(err) = skip_sub_blocks(rdr) {
+            // The frame is still there, even if incomplete
+            self.recover_from_end(err, position)?;
+        }
+        Ok(Some(SkippedFrame {
+            info,
+            delay,
+            local_color_table_size,
+        }))
+    }
+
+    /// Go through the next blocks of the GIF buffer until a graphic rendering
+    /// block is encountered: either an Image Descriptor, whose block ID has
+    /// then just been read, or a whole Plain Text Extension.
+    /// Returns `None` once the Trailer has been reached.
+    fn next_graphic(&mut self, rdr: &mut impl GifRead) -> Result<Option<Graphic>> {
+        if self.ended {
+            return Ok(None);
+        }
+        loop {
+            let position = rdr.get_pos();
+            let block_id = match rdr.read_u8() {
+                Ok(block_id) => block_id,
+                Err(err) => {
+                    // The Trailer is missing
+                    self.recover_from_end(err.into(), position)?;
+                    return Ok(None);
+                }
+            };
+            match block_id {
+                IMAGE_DESCRIPTOR_BLOCK_ID => {
+                    return Ok(Some(Graphic::Image(position)));
+                }
+                TRAILER_BLOCK_ID => {
+                    self.end();
+                    return Ok(None);
+                }
+                EXTENSION_INTRODUCER_ID => match self.parse_extension(rdr) {
+                    Ok(Some(plain_text)) => {
+                        return Ok(Some(Graphic::PlainText(position, plain_text)));
+                    }
+                    Ok(None) => {}
+                    Err(err) => {
+                        self.recover_from_end(err, position)?;
+                        return Ok(None);
+                    }
+                },
+                // Skip garbage until the next known block
+                _ if self.lenient => self.skip_unknown_bytes(position, 1),
+                x => {
+                    return Err(GifParsingError::UnrecognizedBlock {
+                        code: x,
+                        position: rdr.get_pos(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Reset the state to the one it should have just before the frame at the
+    /// index `frame_idx`, described by `info` and `delay`, is encountered:
+    /// right after its Graphic Control Extension, if one.
+    ///
+    /// Resource limits are then counted as if every frame preceding it, of
+    /// `nb_decoded_bytes` pixels in total, had been decoded, whichever frames
+    /// were actually decoded before.
+    pub(crate) fn restore_before_frame(
+        &mut self,
+        frame_idx: usize,
+        info: &FrameInfo,
+        delay: Option<u16>,
+        nb_decoded_bytes: u64,
+    ) {
+        self.ended = false;
+        self.nb_frames = frame_idx;
+        self.nb_decoded_bytes = nb_decoded_bytes;
+        self.last_graphic_ext = Some(GraphicControlExtension {
+            // Not known, but never reported as the frame directly follows
+            position: info.position,
+            has_reserved_bits: false,
+            disposal_method: info.disposal_method,
+            user_input: info.user_input,
+            transparent_color_index: info.transparent_color_index,
+            delay: delay.unwrap_or(0),
+        });
+    }
+
+    /// Indicate that the Trailer has been reached.
+    pub(crate) fn end(&mut self) {
+        self.ended = true;
+        if let Some(unused) = self.last_graphic_ext.take() {
+            self.add_warning(Warning::UnusedGraphicControlExtension {
+                position: unused.position,
+            });
+        }
+        if !self.strict {
+            return;
+        }
+        if let Some((position, uses)) = self.global_color_uses.take() {
+            if !is_sorted_by_uses(&uses) {
+                self.add_warning(Warning::UnsortedColorTable { position });
+            }
+        }
+        if let Some(position) = self.loop_position {
+            if self.nb_frames <= 1 {
+                self.add_warning(Warning::LoopCountOnStillImage { position });
+            }
+        }
+    }
+
+    /// Parse the extension whose Extension Introducer has just been read and
+    /// update the state accordingly.
+    ///
+    /// Plain Text Extensions are graphic rendering blocks, which are returned
+    /// so they can be rendered as a frame (see `plain_text_frame`).
+    ///
+    /// If errors have to be recovered from, unknown and malformed extensions
+    /// are skipped, with a warning.
+    pub(crate) fn parse_extension(&mut self, rdr: &mut impl GifRead) -> Result<Option<PlainText>> {
+        let position = rdr.get_pos() - 1;
+        if self.strict && self.version == Some(GifVersion::Gif87a) {
+            self.add_warning(Warning::ExtensionInGif87a { position });
+        }
+        let label = rdr.read_u8()?;
+        match self.parse_extension_content(rdr, position, label) {
+            Err(err) if self.lenient && !is_unexpected_end(&err) => {
+                self.add_warning(Warning::InvalidExtension { label, position });
+                skip_malformed_extension(rdr, &err)?;
+                Ok(None)
             }
-            TRAILER_BLOCK_ID => {
-                if !found_loop_attribute {
-                    tx.send(GifEvent::LoopingInfo(None)).unwrap_or_else(|err| {
-                        eprintln!(
-                            "Error: Impossible to communicate absence of looping information: {}",
-                            err
-                        );
-                        std::process::exit(1);
+            res => res,
+        }
+    }
+
+    /// Parse the content of the extension whose Extension Introducer was at
+    /// `position` and whose label has just been read.
+    fn parse_extension_content(
+        &mut self,
+        rdr: &mut impl GifRead,
+        position: usize,
+        label: u8,
+    ) -> Result<Option<PlainText>> {
+        match label {
+            GRAPHIC_CONTROL_EXTENSION_LABEL => {
+                let graphic_ext = parse_graphic_control_extension(rdr, position)?;
+                if self.strict && graphic_ext.has_reserved_bits {
+                    self.add_warning(Warning::ReservedBitsSet {
+                        position: position + 3,
                     });
                 }
-                tx.send(GifEvent::FrameEnd).unwrap_or_else(|err| {
-                    eprintln!(
-                        "Error: Impossible to communicate the end of decoded frames: {}",
-                        err
-                    );
-                    std::process::exit(1);
+                if let Some(unused) = self.last_graphic_ext.replace(graphic_ext) {
+                    self.add_warning(Warning::UnusedGraphicControlExtension {
+                        position: unused.position,
+                    });
+                }
+                self.add_extension_kind(ExtensionKind::GraphicControl);
+            }
+            APPLICATION_EXTENSION_LABEL => {
+                let identifier = application_extension::parse_application_identifier(rdr)?;
+                self.add_extension_kind(ExtensionKind::Application(identifier.clone()));
+                self.parse_application_data(rdr, position, identifier)?;
+            }
+            COMMENT_EXTENSION_LABEL => {
+                let data = read_sub_blocks(rdr)?;
+                // The same comment may be encountered again after seeking
+                if !self.comments.iter().any(|c| c.position == position) {
+                    self.comments.push(Comment {
+                        position,
+                        nb_preceding_frames: self.nb_frames,
+                        text: String::from_utf8_lossy(&data).into_owned(),
+                    });
+                }
+                self.add_extension_kind(ExtensionKind::Comment);
+            }
+            PLAIN_TEXT_EXTENSION_LABEL => {
+                let plain_text = plain_text::parse_plain_text_extension(rdr)?;
+                self.add_extension_kind(ExtensionKind::PlainText);
+                return Ok(Some(plain_text));
+            }
+            x if self.lenient => {
+                // Every extension is a succession of sub-blocks after its label
+                skip_sub_blocks(rdr)?;
+                self.add_warning(Warning::UnknownExtension { label: x, position });
+            }
+            x => {
+                return Err(GifParsingError::UnrecognizedExtension(x));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Parse the application data of the Application Extension whose
+    /// Extension Introducer was at `position`, and whose identifier has just
+    /// been read.
+    fn parse_application_data(
+        &mut self,
+        rdr: &mut impl GifRead,
+        position: usize,
+        identifier: String,
+    ) -> Result<()> {
+        let is_known = [
+            NETSCAPE_IDENTIFIER,
+            ANIMEXTS_IDENTIFIER,
+            XMP_IDENTIFIER,
+            ICC_IDENTIFIER,
+        ]
+        .contains(&identifier.as_str());
+        let has_handler = self.application_handlers.has_handler(&identifier);
+        if !is_known && !has_handler {
+            // Nobody cares about that one
+            skip_sub_blocks(rdr)?;
+            if self.strict {
+                self.add_warning(Warning::IgnoredApplicationExtension {
+                    identifier,
+                    position,
                 });
-                break;
             }
-            EXTENSION_INTRODUCER_ID => {
-                match rdr.read_u8()? {
-                    GRAPHIC_CONTROL_EXTENSION_LABEL => {
-                        last_graphic_ext = Some(parse_graphic_control_extension(rdr)?);
-                    }
-                    APPLICATION_EXTENSION_LABEL => {
-                        let extension = parse_application_extension(rdr)?;
-
-                        // Only NETSCAPE2.0 is parsed for now as looping is an essential feature
-                        // (And I just don't want to set it to infinite by default)
-                        if let ApplicationExtension::NetscapeLooping(x) = extension {
-                            found_loop_attribute = true;
-                            tx.send(GifEvent::LoopingInfo(Some(x)))
-                                .unwrap_or_else(|err| {
-                                    eprintln!(
-                                        "Error: Impossible to communicate looping information: {}",
-                                        err
-                                    );
-                                    std::process::exit(1);
-                                });
+            return Ok(());
+        }
+
+        let sub_blocks = application_extension::read_application_data(rdr)?;
+        let data = match identifier.as_str() {
+            NETSCAPE_IDENTIFIER | ANIMEXTS_IDENTIFIER => {
+                for sub_block in &sub_blocks {
+                    // Sub-blocks are never empty, a size of 0 being the block
+                    // terminator
+                    match (sub_block[0], sub_block.len()) {
+                        // Looping sub-block
+                        (0x01, 3) => {
+                            self.loop_count =
+                                Some(u16::from_le_bytes([sub_block[1], sub_block[2]]));
+                            self.loop_position = Some(position);
                         }
-                    }
-                    COMMENT_EXTENSION_LABEL => {
-                        // We don't care about comments
-                        skip_sub_blocks(rdr)?;
-                        if rdr.read_u8()? != 0x00
-                        /* block terminator */
-                        {
-                            panic!("TOTO");
-                            // error::fail_on_expected_block_terminator(Some("Comment"));
+                        // Buffering sub-block
+                        (0x02, 5) => {
+                            let mut size = [0; 4];
+                            size.copy_from_slice(&sub_block[1..]);
+                            self.buffer_size = Some(u32::from_le_bytes(size));
                         }
-                    }
-                    PLAIN_TEXT_EXTENSION_LABEL => {
-                        skip_plain_text_extension(rdr)?;
-                    }
-                    x => {
-                        return Err(GifParsingError::UnrecognizedExtension(x));
+                        (id, length) => self.add_warning(Warning::InvalidNetscapeSubBlock {
+                            position,
+                            id,
+                            length,
+                        }),
                     }
                 }
+                sub_blocks.concat()
             }
-            x => {
-                return Err(GifParsingError::UnrecognizedBlock {
-                    code: x,
-                    position: rdr.get_pos(),
+            XMP_IDENTIFIER => {
+                let packet = application_extension::xmp_packet(&sub_blocks);
+                if self.xmp_packet.is_none() {
+                    self.xmp_packet = Some(packet.clone());
+                }
+                packet
+            }
+            ICC_IDENTIFIER => {
+                let profile = sub_blocks.concat();
+                if self.icc_profile.is_none() {
+                    self.set_icc_profile(profile.clone());
+                }
+                profile
+            }
+            _ => sub_blocks.concat(),
+        };
+        if has_handler {
+            self.application_handlers.handle(&ApplicationExtension {
+                position,
+                identifier,
+                data,
+            });
+        }
+        Ok(())
+    }
+
+    /// Render the given Plain Text Extension, whose Extension Introducer was
+    /// at `position`, as a frame covering its text grid.
+    pub(crate) fn plain_text_frame(
+        &mut self,
+        header: &GifHeader,
+        position: usize,
+        plain_text: PlainText,
+    ) -> Result<RawFrame> {
+        // Plain Text Extensions always use the Global Color Table
+        let mut palette = match &header.global_color_table {
+            None => {
+                return Err(GifParsingError::NoColorTable);
+            }
+            Some(val) => val.clone(),
+        };
+        if let Some(transform) = &self.color_transform {
+            transform.convert_palette(&mut palette);
+        }
+        let (info, delay) = self.plain_text_info(position, plain_text);
+        self.check_frame_limits(&info)?;
+        let indices = info
+            .plain_text
+            .as_ref()
+            .map_or_else(Vec::new, PlainText::render);
+        Ok(RawFrame {
+            nb_decoded: indices.len(),
+            indices,
+            palette,
+            delay,
+            info,
+        })
+    }
+
+    /// Associate the given Plain Text Extension, whose Extension Introducer
+    /// was at `position`, to the last Graphic Control Extension encountered.
+    fn plain_text_info(
+        &mut self,
+        position: usize,
+        plain_text: PlainText,
+    ) -> (FrameInfo, Option<u16>) {
+        let mut info = FrameInfo {
+            position,
+            left: plain_text.left,
+            top: plain_text.top,
+            width: plain_text.width,
+            height: plain_text.height,
+            disposal_method: DisposalMethod::NoDisposalSpecified,
+            transparent_color_index: None,
+            user_input: false,
+            interlaced: false,
+            has_local_color_table: false,
+            plain_text: Some(plain_text),
+        };
+        let delay = self.start_graphic(&mut info);
+        (info, delay)
+    }
+
+    /// Record that an extension of the given kind has been encountered.
+    fn add_extension_kind(&mut self, kind: ExtensionKind) {
+        if !self.extensions.contains(&kind) {
+            self.extensions.push(kind);
+        }
+    }
+
+    /// Parse the Image Descriptor whose block ID has just been read at
+    /// `position`, as well as the Local Color Table and LZW minimum code size
+    /// following it, and prepare the decoding of its image data.
+    pub(crate) fn start_frame(
+        &mut self,
+        rdr: &mut impl GifRead,
+        header: &GifHeader,
+        position: usize,
+    ) -> Result<PartialFrame> {
+        let (descriptor, info, delay) = self.parse_frame_info(rdr, position)?;
+        self.check_frame_limits(&info)?;
+        let mut palette = if descriptor.has_local_color_table {
+            color::parse_color_table(rdr, descriptor.nb_color_entries)?
+        } else {
+            match &header.global_color_table {
+                None => {
+                    return Err(GifParsingError::NoColorTable);
+                }
+                Some(val) => val.clone(),
+            }
+        };
+        if let Some(transform) = &self.color_transform {
+            transform.convert_palette(&mut palette);
+        }
+
+        let initial_code_size = rdr.read_u8()?;
+        let code_size_pos = rdr.get_pos() - 1;
+        if self.strict && !(2..=8).contains(&initial_code_size) {
+            self.add_warning(Warning::LzwCodeSizeOutOfRange {
+                code_size: initial_code_size,
+                position: code_size_pos,
+            });
+        }
+        let decoder = LzwDecoder::new(initial_code_size)
+            .map_err(|err| lzw_error_to_parsing_error(err, code_size_pos))?;
+
+        let width = descriptor.width as usize;
+        let height = descriptor.height as usize;
+
+        let rows = row_order(height, descriptor.interlaced);
+
+        // Only count color uses when they will be checked
+        let color_uses = if descriptor.has_local_color_table {
+            descriptor.is_sorted && self.strict
+        } else {
+            self.global_color_uses.is_some()
+        };
+        let color_uses = if color_uses {
+            Some(vec![0; palette.len()])
+        } else {
+            None
+        };
+        Ok(PartialFrame {
+            row: vec![0; width],
+            rows,
+            nb_decoded: 0,
+            stopped: false,
+            nb_excess: 0,
+            nb_compressed: 0,
+            data_parts: [(0, 0); 3],
+            color_uses,
+            decoder,
+            palette,
+            delay,
+            info,
+        })
+    }
+
+    /// Parse the Image Descriptor whose block ID has just been read at
+    /// `position`, and associate it to the last Graphic Control Extension
+    /// encountered.
+    fn parse_frame_info(
+        &mut self,
+        rdr: &mut impl GifRead,
+        position: usize,
+    ) -> Result<(ImageDescriptor, FrameInfo, Option<u16>)> {
+        let descriptor = parse_image_descriptor(rdr)?;
+        if self.strict && descriptor.has_reserved_bits {
+            self.add_warning(Warning::ReservedBitsSet {
+                position: position + 9,
+            });
+        }
+        let mut info = FrameInfo {
+            position,
+            left: descriptor.left,
+            top: descriptor.top,
+            width: descriptor.width,
+            height: descriptor.height,
+            disposal_method: DisposalMethod::NoDisposalSpecified,
+            transparent_color_index: None,
+            user_input: false,
+            interlaced: descriptor.interlaced,
+            has_local_color_table: descriptor.has_local_color_table,
+            plain_text: None,
+        };
+        let delay = self.start_graphic(&mut info);
+        Ok((descriptor, info, delay))
+    }
+
+    /// Count a new frame, described by `info`, and apply to it the last
+    /// Graphic Control Extension encountered, if one.
+    /// Returns the frame's delay.
+    fn start_graphic(&mut self, info: &mut FrameInfo) -> Option<u16> {
+        self.nb_frames += 1;
+        if let (true, Some((width, height))) = (self.strict, self.screen_size) {
+            if info.left as u32 + info.width as u32 > width as u32
+                || info.top as u32 + info.height as u32 > height as u32
+            {
+                self.add_warning(Warning::FrameOutsideScreen {
+                    position: info.position,
                 });
             }
         }
+        // A Graphic Control Extension only applies to the graphic following it
+        match self.last_graphic_ext.take() {
+            Some(e) => {
+                info.disposal_method = e.disposal_method;
+                info.user_input = e.user_input;
+                info.transparent_color_index = e.transparent_color_index;
+                if e.delay == 0 {
+                    None
+                } else {
+                    Some(e.delay)
+                }
+            }
+            None => None,
+        }
     }
-    Ok(())
 }
 
-enum ApplicationExtension {
-    /// Looping value from the NETSCAPE2.0 extension.
-    /// 0 means infinite looping, any other value would be the number of time
-    /// the GIF image needs to be looped (played back from the beginning).
-    NetscapeLooping(u16),
-    NotKnown,
+/// A graphic rendering block, describing a frame.
+enum Graphic {
+    /// An Image Descriptor, whose block ID was at the given position.
+    Image(usize),
+
+    /// A Plain Text Extension, whose Extension Introducer was at the given
+    /// position.
+    PlainText(usize, PlainText),
+}
+
+/// Information, delay and color table of a frame, whose pixels are elsewhere.
+pub(crate) type FrameParts = (FrameInfo, Option<u16>, Vec<Rgb>);
+
+/// A frame found by `FrameParser::start_next_frame`.
+pub(crate) enum NextFrame {
+    /// A frame from an Image Descriptor, whose image data is still to be
+    /// decoded.
+    Image(Box<PartialFrame>),
+
+    /// A frame from a Plain Text Extension, already rendered.
+    PlainText(RawFrame),
+}
+
+/// Image data of a frame, read from the GIF buffer but not decoded yet. See
+/// `FrameParser::collect_image_data`.
+pub(crate) struct ImageData {
+    /// Content of every sub-block, one after the other.
+    data: Vec<u8>,
+
+    /// Position in the GIF buffer and size of each sub-block, in order.
+    sub_blocks: Vec<(usize, usize)>,
+
+    /// Error which stopped the reading before the block terminator, if it
+    /// could not be recovered from. Only returned once the sub-blocks read
+    /// before it have been decoded, like when decoding while reading.
+    error: Option<GifParsingError>,
+}
+
+impl ImageData {
+    /// Read sub-blocks until the block terminator.
+    /// A sub-block is only kept once it has been entirely read.
+    fn read(&mut self, rdr: &mut impl GifRead) -> Result<()> {
+        loop {
+            let sub_block_size = rdr.read_u8()? as usize;
+            if sub_block_size == 0x00
+            /* block terminator */
+            {
+                return Ok(());
+            }
+            let sub_block_pos = rdr.get_pos();
+            let start = self.data.len();
+            self.data.resize(start + sub_block_size, 0);
+            if let Err(err) = rdr.read_into(&mut self.data[start..]) {
+                self.data.truncate(start);
+                return Err(err.into());
+            }
+            self.sub_blocks.push((sub_block_pos, sub_block_size));
+        }
+    }
+}
+
+/// Information on a frame whose image data has been skipped.
+pub(crate) struct SkippedFrame {
+    /// Information on the frame.
+    pub(crate) info: FrameInfo,
+
+    /// Delay of the frame. See `RawFrame::delay`.
+    pub(crate) delay: Option<u16>,
+
+    /// Number of entries in the frame's Local Color Table, if one.
+    pub(crate) local_color_table_size: Option<usize>,
+}
+
+/// A frame whose image data is being decoded.
+pub(crate) struct PartialFrame {
+    /// Indices of the pixels of the row being decoded. Its length is the
+    /// frame's width.
+    row: Vec<u8>,
+
+    /// Order in which rows are stored in the GIF buffer.
+    rows: Vec<usize>,
+
+    /// Number of pixels decoded until now.
+    nb_decoded: usize,
+
+    /// Set to `true` if the decoding of the frame has been stopped before all
+    /// of its pixels were decoded.
+    stopped: bool,
+
+    /// Number of pixels decoded past the frame's rectangle.
+    nb_excess: usize,
+
+    /// Number of bytes of compressed image data received until now.
+    nb_compressed: usize,
+
+    /// For the last three non-empty parts of the compressed image data
+    /// received, from the last one: their offset in the compressed image data
+    /// and their position in the GIF buffer.
+    /// A LZW code being at most 12 bits long, those are enough to locate the
+    /// byte on which the code being decoded started.
+    data_parts: [(usize, usize); 3],
+
+    /// When decoding strictly, number of uses of each color of the frame's
+    /// color table until now.
+    color_uses: Option<Vec<usize>>,
+
+    /// Decoder for the frame's LZW-compressed image data.
+    decoder: LzwDecoder,
+
+    /// Color table used by the frame.
+    palette: Vec<Rgb>,
+
+    /// Delay of the frame. See `RawFrame::delay`.
+    delay: Option<u16>,
+
+    /// Information on the frame.
+    info: FrameInfo,
+}
+
+impl PartialFrame {
+    /// Decode the given part of the frame's compressed image data, which was
+    /// at the position `data_pos` in the GIF buffer, giving each completed row
+    /// to `sink`.
+    /// Data coming after all pixels have been decoded is only counted (see
+    /// `nb_excess`).
+    pub(crate) fn push_data<S: RowSink + ?Sized>(
+        &mut self,
+        mut data: &[u8],
+        mut data_pos: usize,
+        sink: &mut S,
+    ) -> Result<()> {
+        if self.stopped || data.is_empty() {
+            return Ok(());
+        }
+        self.data_parts.rotate_right(1);
+        self.data_parts[0] = (self.nb_compressed, data_pos);
+        self.nb_compressed += data.len();
+        let width = self.row.len();
+        while !self.is_complete() {
+            // Decode directly into the rest of the current row
+            let out = &mut self.row[self.nb_decoded % width..];
+            let progress = self.decoder.decode_into(data, out);
+            let nb_colors = self.palette.len();
+            if nb_colors < 256 {
+                let decoded = &out[..progress.written];
+                if let Some(invalid) = decoded.iter().position(|&i| i as usize >= nb_colors) {
+                    // Pixels from there have not been decoded
+                    self.nb_decoded += invalid;
+                    return Err(GifParsingError::InvalidColor);
+                }
+            }
+            let is_row_complete = progress.written == out.len();
+            self.nb_decoded += progress.written;
+            if is_row_complete {
+                self.emit_row(self.nb_decoded / width - 1, width, sink);
+            }
+            progress.result.map_err(|err| {
+                let position = match err {
+                    LzwError::InvalidCode { offset, .. } => self.data_position(offset),
+                    _ => data_pos,
+                };
+                lzw_error_to_parsing_error(err, position)
+            })?;
+            if !is_row_complete {
+                // All data was consumed, or the `stop` code was encountered
+                return Ok(());
+            }
+            data = &data[progress.consumed..];
+            data_pos += progress.consumed;
+        }
+
+        // Invalid data there does not matter anymore
+        let mut excess = [0; 1024];
+        loop {
+            let progress = self.decoder.decode_into(data, &mut excess);
+            self.nb_excess += progress.written;
+            if progress.result.is_err() {
+                self.stop();
+                return Ok(());
+            }
+            if progress.written < excess.len() {
+                return Ok(());
+            }
+            data = &data[progress.consumed..];
+        }
+    }
+
+    /// Returns the position in the GIF buffer of the byte at the given offset
+    /// in the compressed image data, which has to be in one of `data_parts`.
+    fn data_position(&self, offset: usize) -> usize {
+        let (start, position) = self
+            .data_parts
+            .iter()
+            .find(|(start, _)| *start <= offset)
+            .unwrap_or(&self.data_parts[2]);
+        position + offset - start
+    }
+
+    /// Give to `sink` the first `nb_pixels` pixels of the row buffer, which
+    /// contains the row decoded at the index `nb_decoded_rows` (rows being
+    /// decoded in the order of `rows`).
+    fn emit_row<S: RowSink + ?Sized>(
+        &mut self,
+        nb_decoded_rows: usize,
+        nb_pixels: usize,
+        sink: &mut S,
+    ) {
+        if let Some(uses) = &mut self.color_uses {
+            for &idx in &self.row[..nb_pixels] {
+                if let Some(nb_uses) = uses.get_mut(idx as usize) {
+                    *nb_uses += 1;
+                }
+            }
+        }
+        sink.put_partial_row(self.rows[nb_decoded_rows], &self.row, nb_pixels);
+    }
+
+    /// Give to `sink` the pixels decoded in the row being decoded, if some
+    /// were. The pixels following them are missing.
+    fn emit_incomplete_row<S: RowSink + ?Sized>(&mut self, sink: &mut S) {
+        let width = self.row.len();
+        if width == 0 || self.nb_decoded == self.nb_pixels() {
+            return;
+        }
+        let nb_pixels = self.nb_decoded % width;
+        if nb_pixels > 0 {
+            self.emit_row(self.nb_decoded / width, nb_pixels, sink);
+        }
+    }
+
+    /// Number of pixels of the frame's rectangle.
+    pub(crate) fn nb_pixels(&self) -> usize {
+        self.row.len() * self.rows.len()
+    }
+
+    /// Returns `true` once all of the frame's pixels have been decoded, or
+    /// once its decoding has been stopped.
+    pub(crate) fn is_complete(&self) -> bool {
+        self.stopped || self.nb_decoded == self.nb_pixels()
+    }
+
+    /// Returns `true` if subsequent image data would be ignored, excess pixels
+    /// included.
+    fn ignores_data(&self) -> bool {
+        self.stopped || self.decoder.is_finished()
+    }
+
+    /// Stop decoding the frame: subsequent image data is ignored.
+    fn stop(&mut self) {
+        self.stopped = true;
+    }
+
+    /// Information on the frame.
+    pub(crate) fn info(&self) -> &FrameInfo {
+        &self.info
+    }
+
+    /// Color table used by the frame.
+    pub(crate) fn palette(&self) -> &[Rgb] {
+        &self.palette
+    }
+
+    /// Position in the GIF buffer of the frame's Image Descriptor.
+    pub(crate) fn position(&self) -> usize {
+        self.info.position
+    }
+
+    /// Returns the frame's information, delay and color table.
+    pub(crate) fn into_parts(self) -> FrameParts {
+        (self.info, self.delay, self.palette)
+    }
+
+    /// Returns the frame, whose pixels were all given to `indices`, as a
+    /// `RawFrame`.
+    pub(crate) fn into_raw_frame(self, indices: Vec<u8>) -> RawFrame {
+        RawFrame {
+            indices,
+            nb_decoded: self.nb_decoded,
+            palette: self.palette,
+            delay: self.delay,
+            info: self.info,
+        }
+    }
+}
+
+/// Destination of the rows of a frame, given as they are decoded.
+pub(crate) trait RowSink {
+    /// Receive the color indices of the first `nb_pixels` pixels of the row at
+    /// the index `y` in the frame's rectangle, `row` being the whole row.
+    /// The pixels following them are missing from the compressed data.
+    fn put_partial_row(&mut self, y: usize, row: &[u8], nb_pixels: usize);
+
+    /// Receive the color indices of the pixels of the row at the index `y` in
+    /// the frame's rectangle.
+    fn put_row(&mut self, y: usize, row: &[u8]) {
+        self.put_partial_row(y, row, row.len());
+    }
+}
+
+/// The indices of all pixels of a frame's rectangle, row by row.
+impl RowSink for [u8] {
+    fn put_partial_row(&mut self, y: usize, row: &[u8], nb_pixels: usize) {
+        let start = y * row.len();
+        self[start..start + nb_pixels].copy_from_slice(&row[..nb_pixels]);
+    }
+}
+
+/// Returns the indexes of the rows of a frame of `height` rows, in the order
+/// in which they are stored in the GIF buffer.
+pub(crate) fn row_order(height: usize, interlaced: bool) -> Vec<usize> {
+    if interlaced {
+        (0..height)
+            .step_by(8)
+            .chain((4..height).step_by(8))
+            .chain((2..height).step_by(4))
+            .chain((1..height).step_by(2))
+            .collect()
+    } else {
+        (0..height).collect()
+    }
+}
+
+/// The different kinds of extension which can be found in a GIF buffer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ExtensionKind {
+    /// A Graphic Control Extension, controlling how the next frame is
+    /// rendered.
+    GraphicControl,
+
+    /// A Comment Extension.
+    Comment,
+
+    /// A Plain Text Extension, describing text to render.
+    PlainText,
+
+    /// An Application Extension, with its application identifier followed by
+    /// its authentication code (e.g. `"NETSCAPE2.0"`).
+    /// Bytes which are not valid UTF-8 are replaced by `U+FFFD`.
+    Application(String),
 }
 
 /// Allows to skip sub-blocks when reached. You might want to do that when
@@ -220,81 +1609,52 @@ fn skip_sub_blocks(rdr: &mut impl GifRead) -> Result<()> {
     }
 }
 
-/// The plain text extention is a 89a GIF extension allowing to render text in a
-/// GIF image. This feature seems to be very rarely used, we can safely ignore
-/// it for now.
-/// TODO?
-fn skip_plain_text_extension(rdr: &mut impl GifRead) -> Result<()> {
-    let block_size = rdr.read_u8()?;
-    if block_size != 12 {
-        return Err(GifParsingError::UnexpectedLength {
-            block_name: "Plain Text Extension".to_owned(),
-            expected: 12,
-            got: block_size,
-        });
+/// Skip the rest of the extension whose content could not be parsed because
+/// of `err`, the reader being where that error was encountered: just after a
+/// sub-block.
+///
+/// The sizes of fixed-size sub-blocks are checked right after being read:
+/// those are then skipped too.
+fn skip_malformed_extension(rdr: &mut impl GifRead, err: &GifParsingError) -> Result<()> {
+    if let GifParsingError::UnexpectedLength { got, .. } = err {
+        rdr.skip_bytes(*got as usize)?;
     }
-    rdr.skip_bytes(12)?; // Skip whole plain text header
-    skip_sub_blocks(rdr)?;
-    Ok(())
+    skip_sub_blocks(rdr)
 }
 
-fn parse_application_extension(rdr: &mut impl GifRead) -> Result<ApplicationExtension> {
-    let block_size = rdr.read_u8()?;
-    if block_size != 11 {
-        return Err(GifParsingError::UnexpectedLength {
-            block_name: "Application Extension".to_owned(),
-            expected: 11,
-            got: block_size,
-        });
-    }
-    let app_name = match rdr.read_str(8) {
-        Err(_) => None,
-        Ok(x) => Some(x),
-    };
-    let app_auth_code = (rdr.read_u8()?, rdr.read_u8()?, rdr.read_u8()?);
-
-    let mut data_len = rdr.read_u8()? as usize;
-    if data_len == 0 {
-        return Ok(ApplicationExtension::NotKnown);
-    }
-
-    let mut ext = ApplicationExtension::NotKnown;
+/// Returns `true` if the colors of a color table, whose number of uses are
+/// given, are sorted by decreasing importance.
+fn is_sorted_by_uses(uses: &[usize]) -> bool {
+    uses.windows(2).all(|pair| pair[0] >= pair[1])
+}
 
-    if app_name == Some("NETSCAPE".to_owned()) && app_auth_code == (50, 46, 48) && data_len >= 3 {
-        let cur_offset;
-        let sub_block_id = rdr.read_u8()?;
-        if data_len != 0x03 || sub_block_id != 0x01 {
-            cur_offset = 1; // Not a valid NETSCAPE2.0 Looping extension, ignore
-        } else {
-            let loop_count = rdr.read_u16()?;
-            ext = ApplicationExtension::NetscapeLooping(loop_count);
-            cur_offset = 3;
-        }
-        data_len -= cur_offset;
+/// Returns `true` if the given error is due to the GIF buffer ending
+/// prematurely.
+fn is_unexpected_end(err: &GifParsingError) -> bool {
+    match err {
+        GifParsingError::IOError(err) => err.kind() == std::io::ErrorKind::UnexpectedEof,
+        _ => false,
     }
+}
 
-    // Skip all remaining data blocks
+/// Read sub-blocks until the block terminator, and return their concatenated
+/// data.
+pub(crate) fn read_sub_blocks(rdr: &mut impl GifRead) -> Result<Vec<u8>> {
+    let mut data = vec![];
     loop {
-        if data_len == 0 {
-            break;
+        let size_of_block = rdr.read_u8()? as usize;
+        if size_of_block == 0 {
+            return Ok(data);
         }
-        rdr.skip_bytes(data_len - 1)?;
-        data_len = rdr.read_u8()? as usize;
+        data.extend(rdr.read_bytes(size_of_block)?);
     }
-    if rdr.read_u8()? != 0x00
-    /* block terminator */
-    {
-        return Err(GifParsingError::ExpectedBlockTerminator {
-            block_name: Some("ApplicationExtension Extension".to_owned()),
-        });
-    }
-    Ok(ext)
 }
 
 /// The available value for the `disposal_method` parsed from a graphic control
 /// extension.
-#[derive(Debug)]
-enum DisposalMethod {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DisposalMethod {
     /// The decoder is not required to take any action.
     NoDisposalSpecified,
 
@@ -312,6 +1672,12 @@ enum DisposalMethod {
 /// Value of a parsed Graphic Control Extension from a GIF buffer
 #[derive(Debug)]
 struct GraphicControlExtension {
+    /// Position in the GIF buffer of the extension's Extension Introducer.
+    position: usize,
+
+    /// If `true`, some of the reserved bits of its packed field are set.
+    has_reserved_bits: bool,
+
     /// Indicates the way in which the graphic is to be treated after being
     /// displayed.
     disposal_method: DisposalMethod,
@@ -322,7 +1688,7 @@ struct GraphicControlExtension {
     /// When a Delay Time is used and the User Input Flag is set, processing
     /// will continue when user input is received or when the delay time
     /// expires, whichever occurs first.
-    _user_input: bool,
+    user_input: bool,
 
     /// The Transparency Index is such that when encountered, the corresponding
     /// pixel of the display device is not modified and processing goes on to
@@ -337,7 +1703,12 @@ struct GraphicControlExtension {
     delay: u16,
 }
 
-fn parse_graphic_control_extension(rdr: &mut impl GifRead) -> Result<GraphicControlExtension> {
+/// Parse a Graphic Control Extension (the `rdr` should be just after its
+/// label), whose Extension Introducer was at `position`.
+fn parse_graphic_control_extension(
+    rdr: &mut impl GifRead,
+    position: usize,
+) -> Result<GraphicControlExtension> {
     let block_size = rdr.read_u8()? as usize;
 
     if block_size != 4 {
@@ -354,7 +1725,7 @@ fn parse_graphic_control_extension(rdr: &mut impl GifRead) -> Result<GraphicCont
         3 => DisposalMethod::RestoreToPrevious,
         _ => DisposalMethod::NoDisposalSpecified,
     };
-    let _user_input: bool = packed_fields & 0x02 != 0;
+    let user_input: bool = packed_fields & 0x02 != 0;
     let transparent_color_flag: bool = packed_fields & 0x01 != 0;
     let delay = rdr.read_u16()?;
     let transparent_color_index = if transparent_color_flag {
@@ -363,148 +1734,172 @@ fn parse_graphic_control_extension(rdr: &mut impl GifRead) -> Result<GraphicCont
         rdr.skip_bytes(1)?;
         None
     };
-    if rdr.read_u8()? != 0 {
+    let block_terminator = rdr.read_u8()?;
+    if block_terminator != 0 {
+        // It is then the size of another sub-block, skipped so that the rest
+        // of the extension can be
+        rdr.skip_bytes(block_terminator as usize)?;
         return Err(GifParsingError::ExpectedBlockTerminator {
             block_name: Some("Graphic Control Extension".to_owned()),
         });
     }
     Ok(GraphicControlExtension {
+        position,
+        has_reserved_bits: packed_fields & 0xE0 != 0,
         disposal_method,
-        _user_input,
+        user_input,
         transparent_color_index,
         delay,
     })
 }
 
-fn construct_next_frame(
-    rdr: &mut impl GifRead,
-    global_color_table: &Option<&[Rgb]>,
-    base_buffer: Option<Vec<u32>>,
-    img_height: u16,
-    img_width: u16,
-    background_color: Option<Rgb>,
-    transparent_color_index: Option<u8>,
-) -> Result<Vec<u32>> {
-    let curr_block_left = rdr.read_u16()?;
-    let curr_block_top = rdr.read_u16()?;
-    let curr_block_width = rdr.read_u16()?;
-    let curr_block_height = rdr.read_u16()?;
-    let field = rdr.read_u8()?;
+/// Value of a parsed Image Descriptor from a GIF buffer
+#[derive(Debug)]
+struct ImageDescriptor {
+    /// Column, in pixels, of the left edge of the image in the logical screen.
+    left: u16,
 
-    let has_local_color_table = field & 0x80 != 0;
+    /// Row, in pixels, of the top edge of the image in the logical screen.
+    top: u16,
 
-    let has_interlacing = field & 0x40 != 0;
-    let _is_sorted = field & 0x20 != 0;
-    let _reserved_1 = field & 0x10;
-    let _reserved_2 = field & 0x08;
-    let nb_color_entries: usize = 1 << ((field & 0x07) + 1);
+    /// Width of the image, in pixels.
+    width: u16,
 
-    // Current interlacing cycle - from 0 to 3 - and step used to obtain the next line
-    // we should draw. Both are only needed when interlacing is enabled.
-    let (mut interlacing_cycle, mut line_step) = if has_interlacing { (0, 8) } else { (0, 1) };
+    /// Height of the image, in pixels.
+    height: u16,
 
-    let lct = if has_local_color_table {
-        Some(color::parse_color_table(rdr, nb_color_entries)?)
-    } else {
-        None
-    };
+    /// If `true`, a Local Color Table immediately follows the Image
+    /// Descriptor.
+    has_local_color_table: bool,
 
-    let current_color_table: &[Rgb] = if let Some(c) = &lct {
-        c
-    } else {
-        match global_color_table {
-            None => {
-                return Err(GifParsingError::NoColorTable);
-            }
-            Some(val) => val,
-        }
-    };
+    /// If `true`, the image is interlaced.
+    interlaced: bool,
 
-    let (has_background_frame, mut global_buffer) = match base_buffer {
-        Some(frame) => (true, frame),
-        None => (false, vec![0; img_height as usize * img_width as usize]),
-    };
+    /// If `true`, the Local Color Table is sorted by decreasing importance.
+    is_sorted: bool,
 
-    let initial_code_size = rdr.read_u8()?;
-    let mut decoder = LzwDecoder::new(initial_code_size);
+    /// If `true`, some of the reserved bits of its packed field are set.
+    has_reserved_bits: bool,
 
-    if curr_block_width == 0 || curr_block_height == 0 {
-        let bg_color: u32 = match background_color {
-            Some(color) => color.into(),
-            None => DEFAULT_BACKGROUND_COLOR,
-        };
-        return Ok(vec![bg_color; img_height as usize * img_width as usize]);
+    /// Number of entries in the Local Color Table, if one.
+    nb_color_entries: usize,
+}
+
+/// Parse an Image Descriptor (the `rdr` should be just after its block ID).
+fn parse_image_descriptor(rdr: &mut impl GifRead) -> Result<ImageDescriptor> {
+    let left = rdr.read_u16()?;
+    let top = rdr.read_u16()?;
+    let width = rdr.read_u16()?;
+    let height = rdr.read_u16()?;
+    let field = rdr.read_u8()?;
+    Ok(ImageDescriptor {
+        left,
+        top,
+        width,
+        height,
+        has_local_color_table: field & 0x80 != 0,
+        interlaced: field & 0x40 != 0,
+        is_sorted: field & 0x20 != 0,
+        has_reserved_bits: field & 0x18 != 0,
+        nb_color_entries: nb_color_entries(field),
+    })
+}
+
+/// Returns the number of entries of the color table whose size is announced by
+/// the given packed field of a Logical Screen Descriptor or Image Descriptor,
+/// if it has one.
+fn nb_color_entries(field: u8) -> usize {
+    1 << ((field & 0x07) + 1)
+}
+
+/// Returns the size, in bytes, of the color table announced by the given
+/// packed field of a Logical Screen Descriptor or Image Descriptor.
+pub(crate) fn color_table_size(field: u8) -> usize {
+    if field & 0x80 != 0 {
+        3 * nb_color_entries(field)
+    } else {
+        0
     }
+}
 
-    let mut x_pos: usize = curr_block_left as usize;
-    let mut y_pos: usize = curr_block_top as usize;
-    let max_pos_width = curr_block_width as usize + curr_block_left as usize - 1;
-    let max_pos_height = curr_block_height as usize + curr_block_top as usize - 1;
-    loop {
-        let sub_block_size = rdr.read_u8()? as usize;
-        if sub_block_size == 0x00
-        /* block terminator */
-        {
-            return Ok(global_buffer);
-        } else {
-            let sub_block_data = rdr.read_bytes(sub_block_size)?;
-            let decoded_data = decoder.decode_next(&sub_block_data);
-            for elt in decoded_data {
-                if elt as usize >= current_color_table.len() {
-                    return Err(GifParsingError::InvalidColor);
-                }
-                let pos = (y_pos * img_width as usize) + x_pos;
-                if pos >= global_buffer.len() {
-                    return Err(GifParsingError::TooMuchPixels);
-                }
-                let pix_val: u32 = match transparent_color_index {
-                    Some(t_idx) if t_idx == elt => {
-                        // transparent color
-                        if has_background_frame {
-                            global_buffer[pos] // do not change anything
-                        } else {
-                            match background_color {
-                                Some(c) => c.into(),
-                                None => DEFAULT_BACKGROUND_COLOR,
-                            }
-                        }
-                    }
-                    _ => (&current_color_table[elt as usize]).into(),
-                };
-                global_buffer[pos] = pix_val;
-                x_pos += 1;
-                if x_pos > max_pos_width {
-                    y_pos += line_step;
-                    if y_pos > max_pos_height {
-                        if !has_interlacing || interlacing_cycle >= 3 {
-                            skip_sub_blocks(rdr)?;
-                            return Ok(global_buffer);
-                        }
-                        interlacing_cycle += 1;
-                        let (new_y_pos, new_line_step) = match interlacing_cycle {
-                            1 => (4, 8),
-                            2 => (2, 4),
-                            _ => (1, 2),
-                        };
-                        y_pos = new_y_pos;
-                        line_step = new_line_step;
-                    }
-                    x_pos = curr_block_left as usize;
-                }
-            }
-        }
+/// Convert an error returned by the `LzwDecoder` into a `GifParsingError`.
+/// `position` is the position in the GIF buffer of the LZW minimum code size
+/// or of the byte on which the invalid code started, depending on the error.
+fn lzw_error_to_parsing_error(err: LzwError, position: usize) -> GifParsingError {
+    match err {
+        LzwError::InvalidMinCodeSize(code_size) => GifParsingError::InvalidLzwCodeSize {
+            code_size,
+            position,
+        },
+        LzwError::InvalidCode { code, .. } => GifParsingError::InvalidLzwCode { code, position },
     }
 }
 
-#[derive(Debug)]
+/// Information parsed from the header of a GIF buffer (the "Header" and the
+/// "Logical Screen Descriptor" blocks, as well as the Global Color Table).
+#[derive(Debug, Clone)]
 pub struct GifHeader {
+    /// Version of the GIF specification the buffer declares to follow.
+    pub version: GifVersion,
+
+    /// Width of the logical screen, in pixels.
     pub width: u16,
+
+    /// Height of the logical screen, in pixels.
     pub height: u16,
+
+    /// Index in the Global Color Table of the background color.
     pub background_color_index: u8,
+
+    /// The Global Color Table, if one.
     pub global_color_table: Option<Vec<Rgb>>,
-    pub _nb_color_resolution_bits: u8,
-    pub _is_table_sorted: bool,
-    pub _pixel_aspect_ratio: u8,
+
+    /// Number of bits per primary color available to the original image.
+    pub color_resolution_bits: u8,
+
+    /// If `true`, the Global Color Table is sorted by decreasing importance.
+    pub is_table_sorted: bool,
+
+    /// Raw "Pixel Aspect Ratio" field, see `pixel_aspect_ratio` for its
+    /// meaning.
+    pub raw_pixel_aspect_ratio: u8,
+
+    /// Position, as returned by the reader the header was parsed from, of the
+    /// packed fields of the Logical Screen Descriptor (the byte just before
+    /// the background color index).
+    pub packed_fields_position: usize,
+}
+
+impl GifHeader {
+    /// Returns the background color defined in the header, if one.
+    pub fn background_color(&self) -> Option<Rgb> {
+        match &self.global_color_table {
+            // An invalid index is reported as a `Warning` by the decoders
+            Some(gct) => gct.get(self.background_color_index as usize).copied(),
+            None => None,
+        }
+    }
+
+    /// Returns the aspect ratio (width divided by height) of a pixel, as
+    /// declared in the header, or `None` if it was not specified (in which
+    /// case pixels should be considered square).
+    pub fn pixel_aspect_ratio(&self) -> Option<f32> {
+        match self.raw_pixel_aspect_ratio {
+            0 => None,
+            x => Some((x as f32 + 15.) / 64.),
+        }
+    }
+}
+
+/// Version of the GIF specification a GIF buffer declares to follow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GifVersion {
+    /// The "87a" version.
+    Gif87a,
+
+    /// The "89a" version, adding extensions (animation, transparency...).
+    Gif89a,
 }
 
 /// Parse Header part of a GIF buffer and the Global Color Table, if one.
@@ -522,27 +1917,29 @@ pub fn parse_header(rdr: &mut impl GifRead) -> Result<GifHeader> {
         Ok(_) => {}
     };
 
-    match rdr.read_str(3) {
+    let version = match rdr.read_str(3) {
         Err(GifReaderStringError::FromUtf8Error) => {
             return Err(GifParsingError::UnsupportedVersion(None));
         }
-        Ok(v) if v != "89a" && v != "87a" => {
+        Ok(v) if v == "87a" => GifVersion::Gif87a,
+        Ok(v) if v == "89a" => GifVersion::Gif89a,
+        Ok(v) => {
             return Err(GifParsingError::UnsupportedVersion(Some(v)));
         }
         Err(GifReaderStringError::IOError(x)) => {
             return Err(GifParsingError::IOError(x));
         }
-        Ok(_) => {}
     };
 
     let width = rdr.read_u16()?;
     let height = rdr.read_u16()?;
 
+    let packed_fields_position = rdr.get_pos();
     let field = rdr.read_u8()?;
     let has_global_color_table = field & 0x80 != 0;
-    let nb_color_resolution_bits = ((field & 0x70) >> 4) + 1;
+    let color_resolution_bits = ((field & 0x70) >> 4) + 1;
     let is_table_sorted = field & 0x08 != 0;
-    let nb_entries: usize = 1 << ((field & 0x07) + 1);
+    let nb_entries = nb_color_entries(field);
 
     let background_color_index = rdr.read_u8()?;
     let pixel_aspect_ratio = rdr.read_u8()?;
@@ -554,12 +1951,74 @@ pub fn parse_header(rdr: &mut impl GifRead) -> Result<GifHeader> {
     };
 
     Ok(GifHeader {
+        version,
         width,
         height,
-        _nb_color_resolution_bits: nb_color_resolution_bits,
-        _is_table_sorted: is_table_sorted,
+        color_resolution_bits,
+        is_table_sorted,
         background_color_index,
-        _pixel_aspect_ratio: pixel_aspect_ratio,
+        raw_pixel_aspect_ratio: pixel_aspect_ratio,
         global_color_table,
+        packed_fields_position,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gif_reader::SliceReader;
+    use crate::test_utils::GifBuilder;
+
+    /// 1x1 GIF whose image data, at the position `37`, contains the invalid
+    /// LZW code `7`, in its first byte.
+    fn build_invalid_image_data() -> Vec<u8> {
+        let mut gif = GifBuilder::new(1, 1, 0).build();
+        gif.pop();
+        gif.extend_from_slice(&[0x2C, 0, 0, 0, 0, 1, 0, 1, 0, 0x00]);
+        // `clear` code then `7`, both 3 bits long
+        gif.extend_from_slice(&[0x02, 0x01, 0x3C, 0x00, 0x3B]);
+        gif
+    }
+
+    #[test]
+    fn reports_invalid_lzw_code_position() {
+        let gif = build_invalid_image_data();
+        let mut decoder = GifDecoder::new(SliceReader::new(&gif)).unwrap();
+        assert!(matches!(
+            decoder.next_frame(),
+            Err(GifParsingError::InvalidLzwCode {
+                code: 7,
+                position: 37
+            })
+        ));
+
+        let options = DecodeOptions {
+            lenient: true,
+            ..Default::default()
+        };
+        let mut decoder = GifDecoder::with_options(SliceReader::new(&gif), options).unwrap();
+        decoder.next_frame().unwrap().unwrap();
+        assert!(decoder.warnings().contains(&Warning::InvalidImageData {
+            frame_position: 25,
+            position: 37,
+        }));
+    }
+
+    #[test]
+    fn reports_position_of_lzw_code_spanning_sub_blocks() {
+        let mut gif = GifBuilder::new(2, 1, 0).build();
+        gif.pop();
+        gif.extend_from_slice(&[0x2C, 0, 0, 0, 0, 2, 0, 1, 0, 0x00, 0x02]);
+        // `clear` code, `1` then `7`, starting at the position `37` and ending
+        // in the next sub-block, at the position `39`
+        gif.extend_from_slice(&[0x01, 0xCC, 0x01, 0x01, 0x00, 0x3B]);
+        let mut decoder = GifDecoder::new(SliceReader::new(&gif)).unwrap();
+        assert!(matches!(
+            decoder.next_frame(),
+            Err(GifParsingError::InvalidLzwCode {
+                code: 7,
+                position: 37
+            })
+        ));
+    }
+}
@@ -0,0 +1,318 @@
+use crate::error::{GifParsingError, Result};
+use crate::gif_reader::GifRead;
+use crate::parser;
+
+/// Width and height, in pixels, of the glyphs of the built-in font.
+const GLYPH_SIZE: usize = 8;
+
+/// Value of a parsed Plain Text Extension from a GIF buffer: text to render as
+/// a grid of character cells over the logical screen, with colors from the
+/// Global Color Table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlainText {
+    /// Column, in pixels, of the left edge of the text grid in the logical
+    /// screen.
+    pub left: u16,
+
+    /// Row, in pixels, of the top edge of the text grid in the logical screen.
+    pub top: u16,
+
+    /// Width of the text grid, in pixels.
+    pub width: u16,
+
+    /// Height of the text grid, in pixels.
+    pub height: u16,
+
+    /// Width of each character cell, in pixels.
+    pub cell_width: u8,
+
+    /// Height of each character cell, in pixels.
+    pub cell_height: u8,
+
+    /// Index in the Global Color Table of the color used for the text.
+    pub foreground_color_index: u8,
+
+    /// Index in the Global Color Table of the color used for the rest of the
+    /// text grid.
+    pub background_color_index: u8,
+
+    /// Character codes of the text to render, one per cell, from left to right
+    /// and top to bottom.
+    pub text: Vec<u8>,
+}
+
+impl PlainText {
+    /// Render the text grid with the built-in monospace font, and return the
+    /// index in the Global Color Table of each of its pixels, row by row.
+    ///
+    /// Glyphs are scaled to the cell size. Characters which do not fit in the
+    /// grid are not rendered, and characters outside the printable ASCII range
+    /// are rendered as spaces.
+    pub fn render(&self) -> Vec<u8> {
+        let width = self.width as usize;
+        let height = self.height as usize;
+        let mut indices = vec![self.background_color_index; width * height];
+
+        let cell_width = self.cell_width as usize;
+        let cell_height = self.cell_height as usize;
+        if cell_width == 0 || cell_height == 0 {
+            return indices;
+        }
+        let nb_columns = width / cell_width;
+        let nb_rows = height / cell_height;
+        for (i, &code) in self.text.iter().take(nb_columns * nb_rows).enumerate() {
+            let glyph = glyph(code);
+            let cell_left = (i % nb_columns) * cell_width;
+            let cell_top = (i / nb_columns) * cell_height;
+            for y in 0..cell_height {
+                let glyph_row = glyph[y * GLYPH_SIZE / cell_height];
+                let row_start = (cell_top + y) * width + cell_left;
+                for x in 0..cell_width {
+                    if glyph_row & (1 << (x * GLYPH_SIZE / cell_width)) != 0 {
+                        indices[row_start + x] = self.foreground_color_index;
+                    }
+                }
+            }
+        }
+        indices
+    }
+}
+
+/// Parse a Plain Text Extension (the `rdr` should be just after its label).
+pub(crate) fn parse_plain_text_extension(rdr: &mut impl GifRead) -> Result<PlainText> {
+    let block_size = rdr.read_u8()?;
+    if block_size != 12 {
+        return Err(GifParsingError::UnexpectedLength {
+            block_name: "Plain Text Extension".to_owned(),
+            expected: 12,
+            got: block_size,
+        });
+    }
+    let left = rdr.read_u16()?;
+    let top = rdr.read_u16()?;
+    let width = rdr.read_u16()?;
+    let height = rdr.read_u16()?;
+    let cell_width = rdr.read_u8()?;
+    let cell_height = rdr.read_u8()?;
+    let foreground_color_index = rdr.read_u8()?;
+    let background_color_index = rdr.read_u8()?;
+
+    let text = parser::read_sub_blocks(rdr)?;
+    Ok(PlainText {
+        left,
+        top,
+        width,
+        height,
+        cell_width,
+        cell_height,
+        foreground_color_index,
+        background_color_index,
+        text,
+    })
+}
+
+/// Returns the glyph of the built-in font for the given character code, as 8
+/// rows whose least significant bit is the leftmost pixel.
+fn glyph(code: u8) -> [u8; GLYPH_SIZE] {
+    match code {
+        0x20..=0x7E => FONT[(code - 0x20) as usize],
+        _ => FONT[0],
+    }
+}
+
+/// 8x8 monospace bitmap font covering the printable ASCII characters, from
+/// `0x20` (space) to `0x7E` (`~`).
+/// Based on the public domain "font8x8" font.
+#[rustfmt::skip]
+const FONT: [[u8; GLYPH_SIZE]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x18, 0x3C, 0x3C, 0x18, 0x18, 0x00, 0x18, 0x00], // '!'
+    [0x36, 0x36, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // '"'
+    [0x36, 0x36, 0x7F, 0x36, 0x7F, 0x36, 0x36, 0x00], // '#'
+    [0x0C, 0x3E, 0x03, 0x1E, 0x30, 0x1F, 0x0C, 0x00], // '$'
+    [0x00, 0x63, 0x33, 0x18, 0x0C, 0x66, 0x63, 0x00], // '%'
+    [0x1C, 0x36, 0x1C, 0x6E, 0x3B, 0x33, 0x6E, 0x00], // '&'
+    [0x06, 0x06, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00], // '''
+    [0x18, 0x0C, 0x06, 0x06, 0x06, 0x0C, 0x18, 0x00], // '('
+    [0x06, 0x0C, 0x18, 0x18, 0x18, 0x0C, 0x06, 0x00], // ')'
+    [0x00, 0x66, 0x3C, 0xFF, 0x3C, 0x66, 0x00, 0x00], // '*'
+    [0x00, 0x0C, 0x0C, 0x3F, 0x0C, 0x0C, 0x00, 0x00], // '+'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C, 0x06], // ','
+    [0x00, 0x00, 0x00, 0x3F, 0x00, 0x00, 0x00, 0x00], // '-'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C, 0x00], // '.'
+    [0x60, 0x30, 0x18, 0x0C, 0x06, 0x03, 0x01, 0x00], // '/'
+    [0x3E, 0x63, 0x73, 0x7B, 0x6F, 0x67, 0x3E, 0x00], // '0'
+    [0x0C, 0x0E, 0x0C, 0x0C, 0x0C, 0x0C, 0x3F, 0x00], // '1'
+    [0x1E, 0x33, 0x30, 0x1C, 0x06, 0x33, 0x3F, 0x00], // '2'
+    [0x1E, 0x33, 0x30, 0x1C, 0x30, 0x33, 0x1E, 0x00], // '3'
+    [0x38, 0x3C, 0x36, 0x33, 0x7F, 0x30, 0x78, 0x00], // '4'
+    [0x3F, 0x03, 0x1F, 0x30, 0x30, 0x33, 0x1E, 0x00], // '5'
+    [0x1C, 0x06, 0x03, 0x1F, 0x33, 0x33, 0x1E, 0x00], // '6'
+    [0x3F, 0x33, 0x30, 0x18, 0x0C, 0x0C, 0x0C, 0x00], // '7'
+    [0x1E, 0x33, 0x33, 0x1E, 0x33, 0x33, 0x1E, 0x00], // '8'
+    [0x1E, 0x33, 0x33, 0x3E, 0x30, 0x18, 0x0E, 0x00], // '9'
+    [0x00, 0x0C, 0x0C, 0x00, 0x00, 0x0C, 0x0C, 0x00], // ':'
+    [0x00, 0x0C, 0x0C, 0x00, 0x00, 0x0C, 0x0C, 0x06], // ';'
+    [0x18, 0x0C, 0x06, 0x03, 0x06, 0x0C, 0x18, 0x00], // '<'
+    [0x00, 0x00, 0x3F, 0x00, 0x00, 0x3F, 0x00, 0x00], // '='
+    [0x06, 0x0C, 0x18, 0x30, 0x18, 0x0C, 0x06, 0x00], // '>'
+    [0x1E, 0x33, 0x30, 0x18, 0x0C, 0x00, 0x0C, 0x00], // '?'
+    [0x3E, 0x63, 0x7B, 0x7B, 0x7B, 0x03, 0x1E, 0x00], // '@'
+    [0x0C, 0x1E, 0x33, 0x33, 0x3F, 0x33, 0x33, 0x00], // 'A'
+    [0x3F, 0x66, 0x66, 0x3E, 0x66, 0x66, 0x3F, 0x00], // 'B'
+    [0x3C, 0x66, 0x03, 0x03, 0x03, 0x66, 0x3C, 0x00], // 'C'
+    [0x1F, 0x36, 0x66, 0x66, 0x66, 0x36, 0x1F, 0x00], // 'D'
+    [0x7F, 0x46, 0x16, 0x1E, 0x16, 0x46, 0x7F, 0x00], // 'E'
+    [0x7F, 0x46, 0x16, 0x1E, 0x16, 0x06, 0x0F, 0x00], // 'F'
+    [0x3C, 0x66, 0x03, 0x03, 0x73, 0x66, 0x7C, 0x00], // 'G'
+    [0x33, 0x33, 0x33, 0x3F, 0x33, 0x33, 0x33, 0x00], // 'H'
+    [0x1E, 0x0C, 0x0C, 0x0C, 0x0C, 0x0C, 0x1E, 0x00], // 'I'
+    [0x78, 0x30, 0x30, 0x30, 0x33, 0x33, 0x1E, 0x00], // 'J'
+    [0x67, 0x66, 0x36, 0x1E, 0x36, 0x66, 0x67, 0x00], // 'K'
+    [0x0F, 0x06, 0x06, 0x06, 0x46, 0x66, 0x7F, 0x00], // 'L'
+    [0x63, 0x77, 0x7F, 0x7F, 0x6B, 0x63, 0x63, 0x00], // 'M'
+    [0x63, 0x67, 0x6F, 0x7B, 0x73, 0x63, 0x63, 0x00], // 'N'
+    [0x1C, 0x36, 0x63, 0x63, 0x63, 0x36, 0x1C, 0x00], // 'O'
+    [0x3F, 0x66, 0x66, 0x3E, 0x06, 0x06, 0x0F, 0x00], // 'P'
+    [0x1E, 0x33, 0x33, 0x33, 0x3B, 0x1E, 0x38, 0x00], // 'Q'
+    [0x3F, 0x66, 0x66, 0x3E, 0x36, 0x66, 0x67, 0x00], // 'R'
+    [0x1E, 0x33, 0x07, 0x0E, 0x38, 0x33, 0x1E, 0x00], // 'S'
+    [0x3F, 0x2D, 0x0C, 0x0C, 0x0C, 0x0C, 0x1E, 0x00], // 'T'
+    [0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x3F, 0x00], // 'U'
+    [0x33, 0x33, 0x33, 0x33, 0x33, 0x1E, 0x0C, 0x00], // 'V'
+    [0x63, 0x63, 0x63, 0x6B, 0x7F, 0x77, 0x63, 0x00], // 'W'
+    [0x63, 0x63, 0x36, 0x1C, 0x1C, 0x36, 0x63, 0x00], // 'X'
+    [0x33, 0x33, 0x33, 0x1E, 0x0C, 0x0C, 0x1E, 0x00], // 'Y'
+    [0x7F, 0x63, 0x31, 0x18, 0x4C, 0x66, 0x7F, 0x00], // 'Z'
+    [0x1E, 0x06, 0x06, 0x06, 0x06, 0x06, 0x1E, 0x00], // '['
+    [0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x40, 0x00], // '\'
+    [0x1E, 0x18, 0x18, 0x18, 0x18, 0x18, 0x1E, 0x00], // ']'
+    [0x08, 0x1C, 0x36, 0x63, 0x00, 0x00, 0x00, 0x00], // '^'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF], // '_'
+    [0x0C, 0x0C, 0x18, 0x00, 0x00, 0x00, 0x00, 0x00], // '`'
+    [0x00, 0x00, 0x1E, 0x30, 0x3E, 0x33, 0x6E, 0x00], // 'a'
+    [0x07, 0x06, 0x06, 0x3E, 0x66, 0x66, 0x3B, 0x00], // 'b'
+    [0x00, 0x00, 0x1E, 0x33, 0x03, 0x33, 0x1E, 0x00], // 'c'
+    [0x38, 0x30, 0x30, 0x3E, 0x33, 0x33, 0x6E, 0x00], // 'd'
+    [0x00, 0x00, 0x1E, 0x33, 0x3F, 0x03, 0x1E, 0x00], // 'e'
+    [0x1C, 0x36, 0x06, 0x0F, 0x06, 0x06, 0x0F, 0x00], // 'f'
+    [0x00, 0x00, 0x6E, 0x33, 0x33, 0x3E, 0x30, 0x1F], // 'g'
+    [0x07, 0x06, 0x36, 0x6E, 0x66, 0x66, 0x67, 0x00], // 'h'
+    [0x0C, 0x00, 0x0E, 0x0C, 0x0C, 0x0C, 0x1E, 0x00], // 'i'
+    [0x30, 0x00, 0x30, 0x30, 0x30, 0x33, 0x33, 0x1E], // 'j'
+    [0x07, 0x06, 0x66, 0x36, 0x1E, 0x36, 0x67, 0x00], // 'k'
+    [0x0E, 0x0C, 0x0C, 0x0C, 0x0C, 0x0C, 0x1E, 0x00], // 'l'
+    [0x00, 0x00, 0x33, 0x7F, 0x7F, 0x6B, 0x63, 0x00], // 'm'
+    [0x00, 0x00, 0x1F, 0x33, 0x33, 0x33, 0x33, 0x00], // 'n'
+    [0x00, 0x00, 0x1E, 0x33, 0x33, 0x33, 0x1E, 0x00], // 'o'
+    [0x00, 0x00, 0x3B, 0x66, 0x66, 0x3E, 0x06, 0x0F], // 'p'
+    [0x00, 0x00, 0x6E, 0x33, 0x33, 0x3E, 0x30, 0x78], // 'q'
+    [0x00, 0x00, 0x3B, 0x6E, 0x66, 0x06, 0x0F, 0x00], // 'r'
+    [0x00, 0x00, 0x3E, 0x03, 0x1E, 0x30, 0x1F, 0x00], // 's'
+    [0x08, 0x0C, 0x3E, 0x0C, 0x0C, 0x2C, 0x18, 0x00], // 't'
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x33, 0x6E, 0x00], // 'u'
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x1E, 0x0C, 0x00], // 'v'
+    [0x00, 0x00, 0x63, 0x6B, 0x7F, 0x7F, 0x36, 0x00], // 'w'
+    [0x00, 0x00, 0x63, 0x36, 0x1C, 0x36, 0x63, 0x00], // 'x'
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x3E, 0x30, 0x1F], // 'y'
+    [0x00, 0x00, 0x3F, 0x19, 0x0C, 0x26, 0x3F, 0x00], // 'z'
+    [0x38, 0x0C, 0x0C, 0x07, 0x0C, 0x0C, 0x38, 0x00], // '{'
+    [0x18, 0x18, 0x18, 0x00, 0x18, 0x18, 0x18, 0x00], // '|'
+    [0x07, 0x0C, 0x0C, 0x38, 0x0C, 0x0C, 0x07, 0x00], // '}'
+    [0x6E, 0x3B, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // '~'
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gif_decoder::decode_from_bytes;
+    use crate::test_utils::GifBuilder;
+
+    /// Pixels of the glyph of `L`, `1` being the foreground.
+    const L_PIXELS: [[u8; 8]; 8] = [
+        [1, 1, 1, 1, 0, 0, 0, 0],
+        [0, 1, 1, 0, 0, 0, 0, 0],
+        [0, 1, 1, 0, 0, 0, 0, 0],
+        [0, 1, 1, 0, 0, 0, 0, 0],
+        [0, 1, 1, 0, 0, 0, 1, 0],
+        [0, 1, 1, 0, 0, 1, 1, 0],
+        [1, 1, 1, 1, 1, 1, 1, 0],
+        [0, 0, 0, 0, 0, 0, 0, 0],
+    ];
+
+    /// Returns the indices of `L_PIXELS` with the given colors.
+    fn l_indices(foreground_color_index: u8, background_color_index: u8) -> Vec<u8> {
+        L_PIXELS
+            .iter()
+            .flatten()
+            .map(|&pixel| match pixel {
+                1 => foreground_color_index,
+                _ => background_color_index,
+            })
+            .collect()
+    }
+
+    fn plain_text(width: u16, height: u16, cell_size: u8, text: &[u8]) -> PlainText {
+        PlainText {
+            left: 0,
+            top: 0,
+            width,
+            height,
+            cell_width: cell_size,
+            cell_height: cell_size,
+            foreground_color_index: 2,
+            background_color_index: 3,
+            text: text.to_vec(),
+        }
+    }
+
+    #[test]
+    fn renders_glyphs_leftmost_bit_first() {
+        assert_eq!(plain_text(8, 8, 8, b"L").render(), l_indices(2, 3));
+    }
+
+    #[test]
+    fn renders_cells_in_order_and_scales_glyphs() {
+        // Two cells of 4x4 pixels: `L`, one pixel out of two, then a space
+        let indices = plain_text(8, 4, 4, b"L\x01L").render();
+        let l = l_indices(2, 3);
+        for y in 0..4 {
+            let row = &indices[y * 8..(y + 1) * 8];
+            let expected: Vec<u8> = (0..4).map(|x| l[y * 2 * 8 + x * 2]).collect();
+            assert_eq!(row[..4], expected[..], "row {}", y);
+            assert_eq!(row[4..], [3; 4], "row {}", y);
+        }
+    }
+
+    #[test]
+    fn renders_plain_text_frame() {
+        let gif = GifBuilder::new(8, 8, 0)
+            .image(0, 0, 8, 8, &[1; 64])
+            .graphic_control(1, Some(0), 7)
+            .plain_text((0, 0, 8, 8), (8, 8), 2, 0, b"L")
+            .build();
+
+        let mut raw_frames = decode_from_bytes(&gif).unwrap().into_raw_frames();
+        raw_frames.next().unwrap().unwrap();
+        let frame = raw_frames.next().unwrap().unwrap();
+        assert_eq!(frame.indices, l_indices(2, 0));
+        assert_eq!(frame.delay, Some(7));
+        assert_eq!(frame.info.transparent_color_index, Some(0));
+        assert!(frame.info.plain_text.is_some());
+
+        // The transparent background lets the previous frame show through
+        let mut decoder = decode_from_bytes(&gif).unwrap();
+        decoder.next_frame().unwrap().unwrap();
+        let frame = decoder.next_frame().unwrap().unwrap();
+        let expected: Vec<u8> = l_indices(2, 1)
+            .iter()
+            .flat_map(|&idx| match idx {
+                2 => [255, 0, 0, 255],
+                _ => [255, 255, 255, 255],
+            })
+            .collect();
+        assert_eq!(frame.data, expected);
+        assert_eq!(frame.delay, Some(7));
+    }
+}
@@ -0,0 +1,269 @@
+use crate::error::Result;
+use crate::gif_reader::GifRead;
+use crate::options::DecodeOptions;
+use crate::parser::{
+    self, Comment, DisposalMethod, ExtensionKind, FrameParser, GifVersion, SkippedFrame,
+};
+use crate::plain_text::PlainText;
+
+/// Summary of a GIF buffer, obtained through `probe` without decoding any of
+/// its image data.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GifInfo {
+    /// Version of the GIF specification the buffer declares to follow.
+    pub version: GifVersion,
+
+    /// Width of the logical screen, in pixels.
+    pub width: u16,
+
+    /// Height of the logical screen, in pixels.
+    pub height: u16,
+
+    /// Aspect ratio (width divided by height) of a pixel, or `None` if it was
+    /// not specified (in which case pixels should be considered square).
+    pub pixel_aspect_ratio: Option<f32>,
+
+    /// Number of entries in the Global Color Table, if one.
+    pub global_color_table_size: Option<usize>,
+
+    /// Index in the Global Color Table of the background color.
+    pub background_color_index: u8,
+
+    /// Looping information: `None` means that the GIF should not loop, `Some(0)`
+    /// that it should loop infinitely and any other value is the number of time
+    /// it should loop.
+    pub loop_count: Option<u16>,
+
+    /// Sum of the delays of every frame, in hundredths (1/100) of a second.
+    pub total_delay: u64,
+
+    /// Information on each frame, in order.
+    pub frames: Vec<ProbedFrame>,
+
+    /// Kinds of extension present in the GIF buffer, in the order in which
+    /// they were first encountered.
+    pub extensions: Vec<ExtensionKind>,
+
+    /// Text of every Comment Extension, in order.
+    pub comments: Vec<Comment>,
+
+    /// XMP packet found in an `"XMP DataXMP"` Application Extension, if one.
+    /// Bytes which are not valid UTF-8 are replaced by `U+FFFD`.
+    pub xmp_packet: Option<String>,
+
+    /// Size, in bytes, of the ICC color profile found in an `"ICCRGBG1012"`
+    /// Application Extension, if one.
+    pub icc_profile_size: Option<usize>,
+
+    /// Size, in bytes, of the buffer the GIF asks to fill before starting
+    /// playback, if indicated by a NETSCAPE2.0 Application Extension.
+    pub buffer_size: Option<u32>,
+}
+
+impl GifInfo {
+    /// Number of frames in the GIF buffer.
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+}
+
+/// Information on a single frame, as obtained through `probe`.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProbedFrame {
+    /// Position in the GIF buffer of the frame's Image Descriptor.
+    pub position: usize,
+
+    /// Column, in pixels, of the left edge of the frame in the logical screen.
+    pub left: u16,
+
+    /// Row, in pixels, of the top edge of the frame in the logical screen.
+    pub top: u16,
+
+    /// Width of the frame, in pixels.
+    pub width: u16,
+
+    /// Height of the frame, in pixels.
+    pub height: u16,
+
+    /// Time, in hundredths (1/100) of a second, during which that frame should
+    /// be displayed.
+    /// `None` if no delay was specified for that frame.
+    pub delay: Option<u16>,
+
+    /// The way in which the frame is to be treated after being displayed.
+    pub disposal_method: DisposalMethod,
+
+    /// Index in the frame's color table of the color considered transparent,
+    /// if one.
+    pub transparent_color_index: Option<u8>,
+
+    /// If `true`, the frame's rows are stored in the interlaced order.
+    pub interlaced: bool,
+
+    /// Number of entries in the frame's Local Color Table, if it has one.
+    /// `None` if it uses the Global Color Table.
+    pub local_color_table_size: Option<usize>,
+
+    /// If the frame comes from a Plain Text Extension instead of an Image
+    /// Descriptor, the text it renders.
+    pub plain_text: Option<PlainText>,
+}
+
+/// Go through a whole GIF buffer, from its very start until its Trailer, and
+/// summarize it.
+///
+/// Only the block structure is read: image data is skipped without ever being
+/// decompressed, making it a cheap way to validate and describe a GIF buffer.
+pub fn probe(rdr: &mut impl GifRead) -> Result<GifInfo> {
+    probe_with_options(rdr, &DecodeOptions::default())
+}
+
+/// Summarize a GIF buffer like `probe`, failing like a `GifDecoder` created
+/// with the same options would when going over `DecodeOptions::limits`.
+/// The other options are ignored.
+pub fn probe_with_options(rdr: &mut impl GifRead, options: &DecodeOptions) -> Result<GifInfo> {
+    let header = parser::parse_header(rdr)?;
+    options
+        .limits
+        .check_screen(&header, options.square_pixels)?;
+    let mut frame_parser = FrameParser::new();
+    frame_parser.set_limits(options.limits);
+    frame_parser.resume_timer();
+    let mut frames: Vec<ProbedFrame> = vec![];
+    let mut total_delay: u64 = 0;
+    while let Some(frame) = frame_parser.skip_frame(rdr)? {
+        let SkippedFrame {
+            info,
+            delay,
+            local_color_table_size,
+        } = frame;
+        total_delay += delay.unwrap_or(0) as u64;
+        frames.push(ProbedFrame {
+            position: info.position,
+            left: info.left,
+            top: info.top,
+            width: info.width,
+            height: info.height,
+            delay,
+            disposal_method: info.disposal_method,
+            transparent_color_index: info.transparent_color_index,
+            interlaced: info.interlaced,
+            local_color_table_size,
+            plain_text: info.plain_text,
+        });
+    }
+    Ok(GifInfo {
+        version: header.version,
+        width: header.width,
+        height: header.height,
+        pixel_aspect_ratio: header.pixel_aspect_ratio(),
+        global_color_table_size: header.global_color_table.as_ref().map(|gct| gct.len()),
+        background_color_index: header.background_color_index,
+        loop_count: frame_parser.loop_count(),
+        total_delay,
+        frames,
+        extensions: frame_parser.extensions().to_vec(),
+        comments: frame_parser.comments().to_vec(),
+        xmp_packet: frame_parser
+            .xmp_packet()
+            .map(|packet| String::from_utf8_lossy(packet).into_owned()),
+        icc_profile_size: frame_parser.icc_profile().map(|profile| profile.len()),
+        buffer_size: frame_parser.buffer_size(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::GifParsingError;
+    use crate::gif_reader::SliceReader;
+    use crate::limits::{Limits, ResourceLimit};
+    use crate::test_utils::build_animation;
+
+    /// Returns `build_animation` with a NETSCAPE2.0 Application Extension
+    /// asking to loop `loop_count` times.
+    fn build_looping_animation(loop_count: u16) -> Vec<u8> {
+        let gif = build_animation();
+        let mut looping = gif[..25].to_vec();
+        looping.extend_from_slice(&[0x21, 0xFF, 0x0B]);
+        looping.extend_from_slice(b"NETSCAPE2.0");
+        looping.extend_from_slice(&[0x03, 0x01]);
+        looping.extend_from_slice(&loop_count.to_le_bytes());
+        looping.push(0x00);
+        looping.extend_from_slice(&gif[25..]);
+        looping
+    }
+
+    #[test]
+    fn summarizes_frames() {
+        let gif = build_looping_animation(3);
+        let info = probe(&mut SliceReader::new(&gif)).unwrap();
+        assert_eq!((info.width, info.height), (2, 2));
+        assert_eq!(info.global_color_table_size, Some(4));
+        assert_eq!(info.loop_count, Some(3));
+        assert_eq!(info.frame_count(), 4);
+        assert!(info.frames.iter().all(|frame| frame.delay == Some(10)));
+        assert_eq!(info.total_delay, 40);
+        let rects: Vec<_> = info
+            .frames
+            .iter()
+            .map(|frame| (frame.left, frame.top, frame.width, frame.height))
+            .collect();
+        assert_eq!(
+            rects,
+            [(0, 0, 2, 2), (0, 0, 1, 1), (1, 0, 1, 1), (0, 0, 2, 2)]
+        );
+        assert_eq!(
+            info.frames[1].disposal_method,
+            DisposalMethod::RestoreToBackgroundColor
+        );
+        assert_eq!(info.frames[3].transparent_color_index, Some(0));
+
+        let info = probe(&mut SliceReader::new(&build_animation())).unwrap();
+        assert_eq!(info.loop_count, None);
+    }
+
+    #[test]
+    fn fails_on_truncated_input() {
+        let gif = build_animation();
+        // In the image data of the last frame
+        let truncated = &gif[..gif.len() - 4];
+        assert!(matches!(
+            probe(&mut SliceReader::new(truncated)),
+            Err(GifParsingError::IOError(err)) if err.kind() == std::io::ErrorKind::UnexpectedEof
+        ));
+        // Without its Trailer
+        let truncated = &gif[..gif.len() - 1];
+        assert!(probe(&mut SliceReader::new(truncated)).is_err());
+    }
+
+    #[test]
+    fn respects_limits() {
+        let gif = build_animation();
+        let options = DecodeOptions {
+            limits: Limits {
+                max_frames: Some(3),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(matches!(
+            probe_with_options(&mut SliceReader::new(&gif), &options),
+            Err(GifParsingError::LimitExceeded(ResourceLimit::Frames))
+        ));
+
+        let options = DecodeOptions {
+            limits: Limits {
+                max_canvas_pixels: Some(3),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(matches!(
+            probe_with_options(&mut SliceReader::new(&gif), &options),
+            Err(GifParsingError::LimitExceeded(ResourceLimit::CanvasPixels))
+        ));
+    }
+}
@@ -0,0 +1,747 @@
+use crate::application_extension::ApplicationExtension;
+use crate::compositor::Compositor;
+use crate::error::{GifParsingError, Result};
+use crate::gif_reader::SliceReader;
+use crate::options::DecodeOptions;
+use crate::parser::{
+    self, color_table_size, FrameParser, GifEvent, GifHeader, PartialFrame, RawFrame,
+    EXTENSION_INTRODUCER_ID, IMAGE_DESCRIPTOR_BLOCK_ID, TRAILER_BLOCK_ID,
+};
+use crate::warning::Warning;
+
+/// Size, in bytes, of the Header and Logical Screen Descriptor blocks.
+const HEADER_SIZE: usize = 13;
+
+/// Size, in bytes, of an Image Descriptor, including its block ID.
+const IMAGE_DESCRIPTOR_SIZE: usize = 10;
+
+/// Push-based GIF decoder: instead of reading from a `GifRead`, it is fed
+/// arbitrary chunks of the GIF buffer as they become available (e.g. from the
+/// network) and returns the events those allowed to produce.
+///
+/// It never blocks waiting for data: when a chunk ends in the middle of a
+/// block, what could be parsed is kept and the rest waits for the next
+/// chunk. Image data is decoded progressively as it comes, one sub-block at a
+/// time: like with a `GifDecoder`, a sub-block is only decoded once it has
+/// been entirely fed, so that both decode damaged GIFs the same way.
+///
+/// Events come in the same order as from `GifDecoder::decode`, except that
+/// the looping information, comments and warnings encountered are returned at
+/// the latest with the chunk whose end they were encountered before.
+///
+/// ```no_run
+/// use gif_renderer::{GifEvent, StreamingDecoder};
+///
+/// # let chunks: Vec<Vec<u8>> = vec![];
+/// let mut decoder = StreamingDecoder::new();
+/// for chunk in chunks {
+///     for event in decoder.feed(&chunk).unwrap() {
+///         if let GifEvent::Frame(frame) = event {
+///             println!("New frame with a delay of {:?}", frame.delay);
+///         }
+///     }
+/// }
+/// ```
+pub struct StreamingDecoder {
+    /// Options used to render frames.
+    options: DecodeOptions,
+
+    /// What the decoder is currently waiting for.
+    state: State,
+
+    /// Bytes fed but not consumed yet, because they form an incomplete block,
+    /// from `buffer_start`.
+    buffer: Vec<u8>,
+
+    /// Offset in `buffer` of its first byte not consumed yet. Consumed bytes
+    /// are only removed once they make up half of it, so that the buffer is
+    /// not shifted on every call.
+    buffer_start: usize,
+
+    /// Position in the GIF buffer of the first byte of `buffer` not consumed
+    /// yet.
+    buffer_pos: usize,
+
+    /// While the extension starting at `buffer_start` has not been entirely
+    /// fed: offset, from its start, of the size of its first sub-block not
+    /// gone through yet. `0` if no extension is waited for.
+    extension_scan: usize,
+
+    /// Header of the GIF, once parsed.
+    header: Option<GifHeader>,
+
+    /// State kept in between frames.
+    frame_parser: FrameParser,
+
+    /// Renders frames over the previous ones, once the header has been parsed.
+    compositor: Option<Compositor>,
+
+    /// Set to `true` once a `GifEvent::LoopingInfo` has been produced.
+    sent_loop_attribute: bool,
+
+    /// Number of comments for which a `GifEvent::Comment` has been produced.
+    nb_sent_comments: usize,
+
+    /// Number of warnings for which a `GifEvent::Warning` has been produced.
+    nb_sent_warnings: usize,
+
+    /// Set to `true` once an error has been encountered, after which fed data
+    /// is ignored.
+    failed: bool,
+
+    /// Error encountered by a `feed` call which also produced events: those
+    /// are returned first, that error being returned by the next call.
+    pending_error: Option<GifParsingError>,
+}
+
+/// What the `StreamingDecoder` is currently waiting for.
+enum State {
+    /// The Header and Logical Screen Descriptor, plus the Global Color Table.
+    Header,
+
+    /// The start of the next block.
+    Block,
+
+    /// The next image data sub-block of a frame.
+    ImageData { frame: Box<PartialFrame> },
+
+    /// Nothing, the Trailer has been reached.
+    Ended,
+}
+
+impl Default for StreamingDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StreamingDecoder {
+    /// Create a new `StreamingDecoder`, with the default options.
+    pub fn new() -> StreamingDecoder {
+        StreamingDecoder::with_options(DecodeOptions::default())
+    }
+
+    /// Create a new `StreamingDecoder`, with the given options.
+    pub fn with_options(options: DecodeOptions) -> StreamingDecoder {
+        let mut frame_parser = FrameParser::new();
+        frame_parser.set_apply_color_profile(options.apply_color_profile);
+        frame_parser.set_lenient(options.lenient);
+        frame_parser.set_strict(options.strict);
+        frame_parser.set_limits(options.limits);
+        StreamingDecoder {
+            options,
+            state: State::Header,
+            buffer: vec![],
+            buffer_start: 0,
+            buffer_pos: 0,
+            extension_scan: 0,
+            header: None,
+            frame_parser,
+            compositor: None,
+            sent_loop_attribute: false,
+            nb_sent_comments: 0,
+            nb_sent_warnings: 0,
+            failed: false,
+            pending_error: None,
+        }
+    }
+
+    /// Returns the header of the GIF, once enough data has been fed to parse
+    /// it.
+    pub fn header(&self) -> Option<&GifHeader> {
+        self.header.as_ref()
+    }
+
+    /// Looping information encountered until now. See
+    /// `GifDecoder::loop_count`.
+    pub fn loop_count(&self) -> Option<u16> {
+        self.frame_parser.loop_count()
+    }
+
+    /// Warnings encountered until now, in order. See `GifDecoder::warnings`.
+    pub fn warnings(&self) -> &[Warning] {
+        self.frame_parser.warnings()
+    }
+
+    /// Register a function which will be called with every Application
+    /// Extension whose identifier is `identifier`. See
+    /// `GifDecoder::register_application_handler`.
+    pub fn register_application_handler<F>(&mut self, identifier: &str, handler: F)
+    where
+        F: FnMut(&ApplicationExtension) + Send + 'static,
+    {
+        self.frame_parser
+            .register_application_handler(identifier, Box::new(handler));
+    }
+
+    /// Returns `true` once the end of the GIF buffer has been reached, after
+    /// which fed data is ignored.
+    pub fn is_finished(&self) -> bool {
+        matches!(self.state, State::Ended)
+    }
+
+    /// Feed the next chunk of the GIF buffer and returns the events it allowed
+    /// to produce, in order.
+    ///
+    /// When an error is encountered after that chunk already produced events,
+    /// those are returned first, the error being returned by the next call to
+    /// `feed` or `finish`. Once an error has been returned, subsequent data is
+    /// ignored.
+    pub fn feed(&mut self, data: &[u8]) -> Result<Vec<GifEvent>> {
+        if let Some(err) = self.pending_error.take() {
+            return Err(err);
+        }
+        if self.failed || self.is_finished() {
+            return Ok(vec![]);
+        }
+        if self.buffer_start * 2 >= self.buffer.len() {
+            self.buffer.drain(..self.buffer_start);
+            self.buffer_start = 0;
+        }
+        self.buffer.extend_from_slice(data);
+        let mut events = vec![];
+        let mut consumed = 0;
+        self.frame_parser.resume_timer();
+        let res = self.process(&mut consumed, &mut events);
+        self.frame_parser.pause_timer();
+        if !self.is_finished() {
+            self.push_pending_events(&mut events);
+        }
+        self.buffer_start += consumed;
+        self.buffer_pos += consumed;
+        match res {
+            Ok(()) => Ok(events),
+            Err(err) => {
+                self.failed = true;
+                if events.is_empty() {
+                    return Err(err);
+                }
+                self.pending_error = Some(err);
+                Ok(events)
+            }
+        }
+    }
+
+    /// Indicate that the whole GIF buffer has been fed, and returns the events
+    /// this allowed to produce, in order.
+    ///
+    /// If its Trailer has not been encountered, this is an error, unless
+    /// decoding leniently (see `DecodeOptions::lenient`): it is then
+    /// considered to be there, the frame being decoded, if one, being kept as
+    /// is.
+    pub fn finish(&mut self) -> Result<Vec<GifEvent>> {
+        if let Some(err) = self.pending_error.take() {
+            return Err(err);
+        }
+        if self.failed || self.is_finished() {
+            return Ok(vec![]);
+        }
+        if !self.frame_parser.is_lenient() || self.header.is_none() {
+            self.failed = true;
+            return Err(GifParsingError::IOError(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "the GIF buffer ended before its Trailer",
+            )));
+        }
+        let mut events = vec![];
+        match std::mem::replace(&mut self.state, State::Ended) {
+            State::ImageData { frame, .. } => {
+                self.frame_parser.end_unexpectedly(frame.position());
+                self.finish_frame(*frame, &mut events);
+            }
+            _ => self.frame_parser.end_unexpectedly(self.buffer_pos),
+        }
+        self.push_end_events(&mut events);
+        self.buffer.clear();
+        self.buffer_start = 0;
+        Ok(events)
+    }
+
+    /// Push to `events`, like `EventSender::send_frame` does before a frame,
+    /// the looping information if not produced yet, then the comments and
+    /// warnings encountered since the last call.
+    fn push_pending_events(&mut self, events: &mut Vec<GifEvent>) {
+        if let (false, Some(x)) = (self.sent_loop_attribute, self.frame_parser.loop_count()) {
+            self.sent_loop_attribute = true;
+            events.push(GifEvent::LoopingInfo(Some(x)));
+        }
+        self.push_comments_and_warnings(events);
+    }
+
+    /// Push to `events` a `GifEvent::Comment` for each comment, then a
+    /// `GifEvent::Warning` for each warning, encountered since the last call.
+    fn push_comments_and_warnings(&mut self, events: &mut Vec<GifEvent>) {
+        let comments = &self.frame_parser.comments()[self.nb_sent_comments..];
+        events.extend(comments.iter().cloned().map(GifEvent::Comment));
+        self.nb_sent_comments = self.frame_parser.comments().len();
+        let warnings = &self.frame_parser.warnings()[self.nb_sent_warnings..];
+        events.extend(warnings.iter().cloned().map(GifEvent::Warning));
+        self.nb_sent_warnings = self.frame_parser.warnings().len();
+    }
+
+    /// Composite the given frame and push it to `events`, after the events
+    /// encountered before it.
+    fn render_frame(&mut self, frame: RawFrame, events: &mut Vec<GifEvent>) {
+        self.push_pending_events(events);
+        if let (Some(compositor), Some(header)) = (&mut self.compositor, &self.header) {
+            update_background_color(compositor, &self.frame_parser, header);
+            events.push(GifEvent::Frame(compositor.render_frame(frame)));
+        }
+    }
+
+    /// End the decoding of the given frame, already drawn on the canvas as its
+    /// image data was fed, and push it to `events`, after the events
+    /// encountered before it.
+    fn finish_frame(&mut self, mut frame: PartialFrame, events: &mut Vec<GifEvent>) {
+        let compositor = match &mut self.compositor {
+            Some(compositor) => compositor,
+            None => unreachable!("frames are only decoded after the header"),
+        };
+        self.frame_parser.finish_frame(&mut frame, compositor);
+        self.push_pending_events(events);
+        let (info, delay, palette) = frame.into_parts();
+        if let Some(compositor) = &mut self.compositor {
+            events.push(GifEvent::Frame(
+                compositor.output_frame(info, delay, palette),
+            ));
+        }
+    }
+
+    /// Push to `events` the events signaling that all frames have been
+    /// decoded, like `EventSender::send_end`.
+    fn push_end_events(&mut self, events: &mut Vec<GifEvent>) {
+        self.push_comments_and_warnings(events);
+        match self.frame_parser.loop_count() {
+            Some(x) if !self.sent_loop_attribute => {
+                events.push(GifEvent::LoopingInfo(Some(x)));
+            }
+            None => events.push(GifEvent::LoopingInfo(None)),
+            _ => {}
+        }
+        events.push(GifEvent::FrameEnd);
+    }
+
+    /// Consume as much of `buffer` as possible, counting the bytes consumed
+    /// from `buffer_start` in `consumed`, and push the corresponding events to
+    /// `events`.
+    fn process(&mut self, consumed: &mut usize, events: &mut Vec<GifEvent>) -> Result<()> {
+        loop {
+            let avail = &self.buffer[self.buffer_start + *consumed..];
+            let pos = self.buffer_pos + *consumed;
+            match &mut self.state {
+                State::Ended => return Ok(()),
+                State::Header => {
+                    if avail.len() < HEADER_SIZE {
+                        return Ok(());
+                    }
+                    let needed = HEADER_SIZE + color_table_size(avail[10]);
+                    if avail.len() < needed {
+                        return Ok(());
+                    }
+                    let mut rdr = SliceReader::with_position(&avail[..needed], pos);
+                    let header = parser::parse_header(&mut rdr)?;
+                    self.options
+                        .limits
+                        .check_screen(&header, self.options.square_pixels)?;
+                    self.frame_parser.check_header(&header);
+                    self.compositor = Some(Compositor::from_header(&header, &self.options));
+                    self.header = Some(header);
+                    self.state = State::Block;
+                    *consumed += needed;
+                }
+                State::Block => {
+                    let frame_parser = &mut self.frame_parser;
+                    let header = match &self.header {
+                        Some(h) => h,
+                        None => unreachable!("blocks are only parsed after the header"),
+                    };
+                    let block_id = match avail.first() {
+                        Some(b) => *b,
+                        None => return Ok(()),
+                    };
+                    match block_id {
+                        IMAGE_DESCRIPTOR_BLOCK_ID => {
+                            if avail.len() < IMAGE_DESCRIPTOR_SIZE {
+                                return Ok(());
+                            }
+                            // Also wait for the Local Color Table and LZW minimum code size
+                            let needed = IMAGE_DESCRIPTOR_SIZE + color_table_size(avail[9]) + 1;
+                            if avail.len() < needed {
+                                return Ok(());
+                            }
+                            let mut rdr = SliceReader::with_position(&avail[1..needed], pos + 1);
+                            let frame = frame_parser.start_frame(&mut rdr, header, pos)?;
+                            if let Some(compositor) = &mut self.compositor {
+                                // Rows are drawn on the canvas as they are decoded
+                                update_background_color(compositor, frame_parser, header);
+                                compositor.begin_frame(frame.info(), frame.palette());
+                            }
+                            self.state = State::ImageData {
+                                frame: Box::new(frame),
+                            };
+                            *consumed += needed;
+                        }
+                        TRAILER_BLOCK_ID => {
+                            frame_parser.end();
+                            self.push_end_events(events);
+                            self.state = State::Ended;
+                            *consumed += 1;
+                        }
+                        EXTENSION_INTRODUCER_ID => {
+                            let size = extension_size(avail, &mut self.extension_scan);
+                            // The extension is at least as big as what was gone through
+                            self.options
+                                .limits
+                                .check_extension_size(self.extension_scan)?;
+                            let needed = match size {
+                                Some(size) => size,
+                                None => return Ok(()),
+                            };
+                            self.extension_scan = 0;
+                            let mut rdr = SliceReader::with_position(&avail[1..needed], pos + 1);
+                            let plain_text = frame_parser.parse_extension(&mut rdr)?;
+                            let plain_text_frame = match plain_text {
+                                Some(plain_text) => {
+                                    Some(frame_parser.plain_text_frame(header, pos, plain_text)?)
+                                }
+                                None => None,
+                            };
+                            *consumed += needed;
+                            if let Some(frame) = plain_text_frame {
+                                self.render_frame(frame, events);
+                            }
+                        }
+                        // Skip garbage until the next known block
+                        _ if frame_parser.is_lenient() => {
+                            let block_ids = [
+                                IMAGE_DESCRIPTOR_BLOCK_ID,
+                                TRAILER_BLOCK_ID,
+                                EXTENSION_INTRODUCER_ID,
+                            ];
+                            let nb_bytes =
+                                avail.iter().take_while(|b| !block_ids.contains(b)).count();
+                            if nb_bytes == avail.len() {
+                                // Wait for the end of the garbage, so it is
+                                // reported at once
+                                return Ok(());
+                            }
+                            frame_parser.skip_unknown_bytes(pos, nb_bytes);
+                            *consumed += nb_bytes;
+                        }
+                        code => {
+                            return Err(GifParsingError::UnrecognizedBlock {
+                                code,
+                                position: pos + 1,
+                            });
+                        }
+                    }
+                }
+                State::ImageData { frame } => {
+                    let sub_block_size = match avail.first() {
+                        Some(b) => *b as usize,
+                        None => return Ok(()),
+                    };
+                    if sub_block_size == 0x00
+                    /* block terminator */
+                    {
+                        *consumed += 1;
+                        let state = std::mem::replace(&mut self.state, State::Block);
+                        if let State::ImageData { frame } = state {
+                            self.finish_frame(*frame, events);
+                        }
+                        continue;
+                    }
+                    // Wait for the whole sub-block
+                    let needed = 1 + sub_block_size;
+                    if avail.len() < needed {
+                        return Ok(());
+                    }
+                    let compositor = match &mut self.compositor {
+                        Some(compositor) => compositor,
+                        None => unreachable!("frames are only decoded after the header"),
+                    };
+                    self.frame_parser.push_image_data(
+                        frame,
+                        &avail[1..needed],
+                        pos + 1,
+                        compositor,
+                    )?;
+                    *consumed += needed;
+                }
+            }
+        }
+    }
+}
+
+/// Convert the background color to sRGB, if colors have to be converted from
+/// an ICC color profile. See `GifDecoder::update_background_color`.
+pub(crate) fn update_background_color(
+    compositor: &mut Compositor,
+    frame_parser: &FrameParser,
+    header: &GifHeader,
+) {
+    if let Some(transform) = frame_parser.color_transform() {
+        let background_color = header.background_color().map(|c| transform.convert(c));
+        compositor.set_background_color(background_color);
+    }
+}
+
+/// Returns the size, in bytes, of the extension at the start of `buf`
+/// (Extension Introducer and block terminator included), or `None` if `buf`
+/// does not contain the whole extension yet.
+///
+/// `scan` is the offset, from the start of `buf`, of the size of the first
+/// sub-block not gone through yet by a previous call, or `0`. It is updated
+/// so that the next call starts from there, and ends up being the size of
+/// the extension once it is entirely in `buf`.
+fn extension_size(buf: &[u8], scan: &mut usize) -> Option<usize> {
+    // Every extension is, after its label, a succession of sub-blocks
+    *scan = (*scan).max(2);
+    loop {
+        let sub_block_size = *buf.get(*scan)? as usize;
+        *scan += 1 + sub_block_size;
+        if sub_block_size == 0 {
+            return Some(*scan);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gif_decoder::{decode_from_bytes, GifDecoder};
+    use crate::gif_reader::SliceReader;
+    use crate::limits::{Limits, ResourceLimit};
+    use crate::test_utils::{build_animation, GifBuilder};
+
+    /// Returns the pixels of every frame among `events`.
+    fn frames_data(events: &[GifEvent]) -> Vec<Vec<u8>> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                GifEvent::Frame(frame) => Some(frame.data.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn decodes_chunks_of_any_size() {
+        let gif = build_animation();
+        let expected: Vec<Vec<u8>> = decode_from_bytes(&gif)
+            .unwrap()
+            .map(|frame| frame.unwrap().data)
+            .collect();
+        for chunk_size in &[1, 2, 3, 7, 16, gif.len()] {
+            let mut decoder = StreamingDecoder::new();
+            let mut events = vec![];
+            for chunk in gif.chunks(*chunk_size) {
+                events.extend(decoder.feed(chunk).unwrap());
+            }
+            assert!(decoder.is_finished());
+            assert!(decoder.finish().unwrap().is_empty());
+            assert_eq!(frames_data(&events), expected, "chunks of {}", chunk_size);
+            assert!(matches!(events.last(), Some(GifEvent::FrameEnd)));
+        }
+    }
+
+    #[test]
+    fn returns_events_decoded_before_error() {
+        let mut gif = GifBuilder::new(2, 1, 0).image(0, 0, 2, 1, &[1, 2]).build();
+        // Replace the Trailer by an unknown block
+        *gif.last_mut().unwrap() = 0x42;
+
+        let mut decoder = StreamingDecoder::new();
+        let events = decoder.feed(&gif).unwrap();
+        assert_eq!(frames_data(&events).len(), 1);
+        assert!(matches!(
+            decoder.feed(&[]),
+            Err(GifParsingError::UnrecognizedBlock { code: 0x42, .. })
+        ));
+        assert!(decoder.feed(&[0x3B]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn decodes_truncated_sub_block_like_gif_decoder() {
+        let indices: Vec<u8> = (0..64).map(|i| i % 4).collect();
+        let gif = GifBuilder::new(8, 8, 0).image(0, 0, 8, 8, &indices).build();
+        // Header, Image Descriptor, LZW minimum code size and first sub-block,
+        // then half of the second sub-block
+        let gif = &gif[..13 + 12 + 10 + 1 + 17 + 8];
+        let options = DecodeOptions {
+            lenient: true,
+            ..Default::default()
+        };
+
+        let mut decoder = GifDecoder::with_options(SliceReader::new(gif), options.clone()).unwrap();
+        let frame = decoder.next_frame().unwrap().unwrap();
+        assert!(decoder.next_frame().unwrap().is_none());
+
+        let mut streaming_decoder = StreamingDecoder::with_options(options);
+        let mut events = streaming_decoder.feed(gif).unwrap();
+        events.extend(streaming_decoder.finish().unwrap());
+        assert_eq!(frames_data(&events), [frame.data]);
+        assert_eq!(streaming_decoder.warnings(), decoder.warnings());
+        // Only the pixels of the first sub-block are decoded
+        assert!(decoder.warnings().contains(&Warning::IncompleteFrame {
+            position: 25,
+            nb_decoded: 28,
+            nb_pixels: 64,
+        }));
+    }
+
+    #[test]
+    fn skips_malformed_extension_like_gif_decoder() {
+        let gif = GifBuilder::new(2, 1, 0).build();
+        let mut gif = gif[..gif.len() - 1].to_vec();
+        // Graphic Control Extension with a block of 5 bytes, which look like
+        // a Trailer
+        gif.extend_from_slice(&[0x21, 0xF9, 0x05, 0x3B, 0, 0, 0, 0, 0x00]);
+        // Graphic Control Extension not terminated after its block, the next
+        // sub-block also looking like a Trailer
+        gif.extend_from_slice(&[0x21, 0xF9, 0x04, 0, 0, 0, 0, 0x01, 0x3B, 0x00]);
+        gif.extend_from_slice(&GifBuilder::new(2, 1, 0).image(0, 0, 2, 1, &[1, 2]).build()[25..]);
+        let options = DecodeOptions {
+            lenient: true,
+            ..Default::default()
+        };
+
+        let decoder = GifDecoder::with_options(SliceReader::new(&gif), options.clone()).unwrap();
+        let mut raw_frames = decoder.into_raw_frames();
+        assert_eq!(raw_frames.next().unwrap().unwrap().indices, [1, 2]);
+        assert!(raw_frames.next().is_none());
+        let warnings = raw_frames.decoder().warnings();
+        assert_eq!(
+            warnings,
+            [
+                Warning::InvalidExtension {
+                    label: 0xF9,
+                    position: 25
+                },
+                Warning::InvalidExtension {
+                    label: 0xF9,
+                    position: 34
+                }
+            ]
+        );
+
+        let mut streaming_decoder = StreamingDecoder::with_options(options);
+        let events = streaming_decoder.feed(&gif).unwrap();
+        assert_eq!(frames_data(&events).len(), 1);
+        assert_eq!(streaming_decoder.warnings(), warnings);
+    }
+
+    #[test]
+    fn sends_comments_like_gif_decoder() {
+        // Comments before the looping information, in between frames and
+        // before the Trailer
+        let gif = GifBuilder::new(2, 1, 0)
+            .comment(b"first")
+            .application_extension(b"NETSCAPE2.0", &[&[0x01, 0x00, 0x00]])
+            .image(0, 0, 2, 1, &[1, 2])
+            .comment(b"second")
+            .comment(b"third")
+            .image(0, 0, 2, 1, &[2, 1])
+            .comment(b"last")
+            .build();
+        let describe = |event: &GifEvent| match event {
+            GifEvent::Frame(_) => "frame".to_owned(),
+            GifEvent::Comment(comment) => {
+                format!("{}@{}", comment.text, comment.nb_preceding_frames)
+            }
+            event => format!("{:?}", event),
+        };
+        let expected = [
+            "LoopingInfo(Some(0))",
+            "first@0",
+            "frame",
+            "second@1",
+            "third@1",
+            "frame",
+            "last@2",
+            "FrameEnd",
+        ];
+
+        let mut decoder = decode_from_bytes(&gif).unwrap();
+        let mut nb_frames = 0;
+        while decoder.next_frame().unwrap().is_some() {
+            nb_frames += 1;
+            let comments: Vec<_> = decoder.comments().iter().map(|c| &c.text).collect();
+            match nb_frames {
+                1 => assert_eq!(comments, ["first"]),
+                _ => assert_eq!(comments, ["first", "second", "third"]),
+            }
+        }
+        assert_eq!(decoder.comments().len(), 4);
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        decode_from_bytes(&gif).unwrap().decode(tx).unwrap();
+        let events: Vec<_> = rx.try_iter().map(|event| describe(&event)).collect();
+        assert_eq!(events, expected);
+
+        let mut decoder = StreamingDecoder::new();
+        let events: Vec<_> = decoder.feed(&gif).unwrap().iter().map(describe).collect();
+        assert_eq!(events, expected);
+    }
+
+    /// Returns a GIF with a Comment Extension of `nb_sub_blocks` sub-blocks of
+    /// 255 bytes, before a frame.
+    fn build_long_comment(nb_sub_blocks: usize) -> Vec<u8> {
+        let gif = GifBuilder::new(2, 1, 0).image(0, 0, 2, 1, &[1, 2]).build();
+        let mut with_comment = gif[..25].to_vec();
+        with_comment.extend_from_slice(&[0x21, 0xFE]);
+        for _ in 0..nb_sub_blocks {
+            with_comment.push(255);
+            with_comment.extend_from_slice(&[b'a'; 255]);
+        }
+        with_comment.push(0x00);
+        with_comment.extend_from_slice(&gif[25..]);
+        with_comment
+    }
+
+    #[test]
+    fn buffers_long_extension_fed_byte_by_byte() {
+        let gif = build_long_comment(256);
+        let mut decoder = StreamingDecoder::new();
+        let mut events = vec![];
+        for byte in &gif {
+            events.extend(decoder.feed(&[*byte]).unwrap());
+        }
+        assert!(decoder.is_finished());
+        assert_eq!(frames_data(&events).len(), 1);
+        let comments: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                GifEvent::Comment(comment) => Some(comment.text.len()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(comments, [256 * 255]);
+    }
+
+    #[test]
+    fn limits_size_of_buffered_extension() {
+        let gif = build_long_comment(8);
+        let options = DecodeOptions {
+            limits: Limits {
+                max_extension_size: Some(1000),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        // Fails as soon as the extension is known to be too big, before it is
+        // entirely fed
+        let mut decoder = StreamingDecoder::with_options(options.clone());
+        for chunk in gif[..25 + 2 + 3 * 256].chunks(100) {
+            assert!(decoder.feed(chunk).is_ok());
+        }
+        assert!(matches!(
+            decoder.feed(&gif[25 + 2 + 3 * 256..25 + 2 + 4 * 256 + 1]),
+            Err(GifParsingError::LimitExceeded(ResourceLimit::ExtensionSize))
+        ));
+
+        let gif = build_long_comment(3);
+        let mut decoder = StreamingDecoder::with_options(options);
+        assert_eq!(frames_data(&decoder.feed(&gif).unwrap()).len(), 1);
+    }
+}
@@ -0,0 +1,184 @@
+//! Helpers building GIF buffers for the tests.
+
+use crate::color::Rgb;
+
+/// Global Color Table of the GIF buffers built by a `GifBuilder`.
+pub(crate) const PALETTE: [Rgb; 4] = [
+    Rgb { r: 0, g: 0, b: 0 },
+    Rgb {
+        r: 255,
+        g: 255,
+        b: 255,
+    },
+    Rgb { r: 255, g: 0, b: 0 },
+    Rgb { r: 0, g: 255, b: 0 },
+];
+
+/// Maximum size of the image data sub-blocks written by a `GifBuilder`, kept
+/// small so that frames span several of them.
+const SUB_BLOCK_SIZE: usize = 16;
+
+/// Build a GIF89a buffer, block by block.
+pub(crate) struct GifBuilder {
+    bytes: Vec<u8>,
+}
+
+impl GifBuilder {
+    /// Start a GIF buffer with a logical screen of the given dimensions, and
+    /// `PALETTE` as Global Color Table.
+    pub(crate) fn new(width: u16, height: u16, background_color_index: u8) -> GifBuilder {
+        let mut bytes = b"GIF89a".to_vec();
+        bytes.extend_from_slice(&width.to_le_bytes());
+        bytes.extend_from_slice(&height.to_le_bytes());
+        // Global Color Table of 4 entries
+        bytes.extend_from_slice(&[0x81, background_color_index, 0]);
+        for color in &PALETTE {
+            bytes.extend_from_slice(&[color.r, color.g, color.b]);
+        }
+        GifBuilder { bytes }
+    }
+
+    /// Add a Graphic Control Extension.
+    pub(crate) fn graphic_control(
+        mut self,
+        disposal_method: u8,
+        transparent_color_index: Option<u8>,
+        delay: u16,
+    ) -> GifBuilder {
+        let packed = (disposal_method << 2) | transparent_color_index.is_some() as u8;
+        self.bytes.extend_from_slice(&[0x21, 0xF9, 0x04, packed]);
+        self.bytes.extend_from_slice(&delay.to_le_bytes());
+        self.bytes
+            .extend_from_slice(&[transparent_color_index.unwrap_or(0), 0x00]);
+        self
+    }
+
+    /// Add a Comment Extension containing `text`.
+    pub(crate) fn comment(mut self, text: &[u8]) -> GifBuilder {
+        self.bytes.extend_from_slice(&[0x21, 0xFE]);
+        for sub_block in text.chunks(SUB_BLOCK_SIZE) {
+            self.bytes.push(sub_block.len() as u8);
+            self.bytes.extend_from_slice(sub_block);
+        }
+        self.bytes.push(0x00);
+        self
+    }
+
+    /// Add an Application Extension with the given identifier (authentication
+    /// code included) and sub-blocks of application data.
+    pub(crate) fn application_extension(
+        mut self,
+        identifier: &[u8; 11],
+        sub_blocks: &[&[u8]],
+    ) -> GifBuilder {
+        self.bytes.extend_from_slice(&[0x21, 0xFF, 0x0B]);
+        self.bytes.extend_from_slice(identifier);
+        for sub_block in sub_blocks {
+            self.bytes.push(sub_block.len() as u8);
+            self.bytes.extend_from_slice(sub_block);
+        }
+        self.bytes.push(0x00);
+        self
+    }
+
+    /// Add a Plain Text Extension rendering `text` in a grid of the given
+    /// rectangle, made of cells of the given size.
+    pub(crate) fn plain_text(
+        mut self,
+        (left, top, width, height): (u16, u16, u16, u16),
+        (cell_width, cell_height): (u8, u8),
+        foreground_color_index: u8,
+        background_color_index: u8,
+        text: &[u8],
+    ) -> GifBuilder {
+        self.bytes.extend_from_slice(&[0x21, 0x01, 0x0C]);
+        for field in &[left, top, width, height] {
+            self.bytes.extend_from_slice(&field.to_le_bytes());
+        }
+        self.bytes.extend_from_slice(&[
+            cell_width,
+            cell_height,
+            foreground_color_index,
+            background_color_index,
+        ]);
+        for sub_block in text.chunks(SUB_BLOCK_SIZE) {
+            self.bytes.push(sub_block.len() as u8);
+            self.bytes.extend_from_slice(sub_block);
+        }
+        self.bytes.push(0x00);
+        self
+    }
+
+    /// Add an Image Descriptor for the given rectangle, followed by its image
+    /// data: the given color indices in the Global Color Table.
+    pub(crate) fn image(
+        mut self,
+        left: u16,
+        top: u16,
+        width: u16,
+        height: u16,
+        indices: &[u8],
+    ) -> GifBuilder {
+        self.bytes.push(0x2C);
+        for field in &[left, top, width, height] {
+            self.bytes.extend_from_slice(&field.to_le_bytes());
+        }
+        self.bytes.extend_from_slice(&[0x00, 0x02]);
+        for sub_block in lzw_encode(indices).chunks(SUB_BLOCK_SIZE) {
+            self.bytes.push(sub_block.len() as u8);
+            self.bytes.extend_from_slice(sub_block);
+        }
+        self.bytes.push(0x00);
+        self
+    }
+
+    /// Add the Trailer and return the whole GIF buffer.
+    pub(crate) fn build(mut self) -> Vec<u8> {
+        self.bytes.push(0x3B);
+        self.bytes
+    }
+}
+
+/// Build a 2x2 GIF whose frames use every disposal method.
+pub(crate) fn build_animation() -> Vec<u8> {
+    GifBuilder::new(2, 2, 0)
+        .graphic_control(1, None, 10)
+        .image(0, 0, 2, 2, &[1, 1, 1, 1])
+        .graphic_control(2, None, 10)
+        .image(0, 0, 1, 1, &[2])
+        .graphic_control(3, None, 10)
+        .image(1, 0, 1, 1, &[3])
+        .graphic_control(1, Some(0), 10)
+        .image(0, 0, 2, 2, &[0, 2, 0, 3])
+        .build()
+}
+
+/// Compress color indices, all under `4`, with a LZW minimum code size of `2`.
+///
+/// No compression is actually done: a `clear` code is inserted every two
+/// indices so that codes stay 3 bits long.
+pub(crate) fn lzw_encode(indices: &[u8]) -> Vec<u8> {
+    let mut codes = vec![];
+    for pair in indices.chunks(2) {
+        codes.push(4);
+        codes.extend(pair.iter().map(|&idx| idx as u32));
+    }
+    codes.push(5);
+
+    let mut bytes = vec![];
+    let mut acc = 0;
+    let mut nb_bits = 0;
+    for code in codes {
+        acc |= code << nb_bits;
+        nb_bits += 3;
+        while nb_bits >= 8 {
+            bytes.push(acc as u8);
+            acc >>= 8;
+            nb_bits -= 8;
+        }
+    }
+    if nb_bits > 0 {
+        bytes.push(acc as u8);
+    }
+    bytes
+}
@@ -0,0 +1,409 @@
+use std::fmt;
+
+/// A deviation from the GIF specification which was encountered while
+/// decoding, but which did not prevent it from going on.
+///
+/// Some of them can only be encountered when decoding leniently (see
+/// `DecodeOptions::lenient`), as they are errors otherwise, and other ones only
+/// when decoding strictly (see `DecodeOptions::strict`).
+///
+/// Positions are offsets, in bytes, from the start of the GIF buffer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Warning {
+    /// The GIF buffer ended before its Trailer, in the block at `position`
+    /// (or at the end of the last block, if `position` is the size of the GIF
+    /// buffer).
+    /// Decoding ended there, as if the Trailer was present.
+    UnexpectedEnd { position: usize },
+
+    /// Only `nb_decoded` of the `nb_pixels` pixels of the frame whose Image
+    /// Descriptor is at `position` could be decoded.
+    /// The other ones are left untouched: the canvas keeps its previous
+    /// content there.
+    IncompleteFrame {
+        position: usize,
+        nb_decoded: usize,
+        nb_pixels: usize,
+    },
+
+    /// The image data of the frame whose Image Descriptor is at
+    /// `frame_position` is invalid from `position`: it contains a LZW code
+    /// corresponding to no value or a color index out of its color table.
+    /// The rest of its image data was ignored.
+    InvalidImageData {
+        frame_position: usize,
+        position: usize,
+    },
+
+    /// An extension with an unknown label was found at `position`. It was
+    /// skipped.
+    UnknownExtension { label: u8, position: usize },
+
+    /// The extension at `position` is malformed. It was ignored.
+    InvalidExtension { label: u8, position: usize },
+
+    /// `length` bytes which do not start any known block were found at
+    /// `position`. They were skipped.
+    SkippedBytes { position: usize, length: usize },
+
+    /// The background color index, at `position` in the Logical Screen
+    /// Descriptor, is out of the Global Color Table.
+    /// The default background color (white) is used instead.
+    InvalidBackgroundColorIndex { index: u8, position: usize },
+
+    /// A NETSCAPE2.0 (or ANIMEXTS1.0) Application Extension, at `position`,
+    /// contains a sub-block with an unknown ID or whose length does not match
+    /// it (3 bytes for the looping sub-block, whose ID is `1`, and 5 bytes for
+    /// the buffering one, whose ID is `2`). That sub-block was ignored.
+    InvalidNetscapeSubBlock {
+        position: usize,
+        id: u8,
+        length: usize,
+    },
+
+    /// An Application Extension, at `position`, was ignored as its identifier
+    /// (followed by its authentication code) is not known, and as no handler
+    /// was registered for it.
+    /// Only reported when decoding strictly.
+    IgnoredApplicationExtension { identifier: String, position: usize },
+
+    /// The Graphic Control Extension at `position` was not followed by any
+    /// graphic rendering block to apply to, and was thus ignored.
+    UnusedGraphicControlExtension { position: usize },
+
+    /// The image data of the frame whose Image Descriptor is at `position`
+    /// continues past its rectangle: it contains `nb_excess` more pixels than
+    /// it should. Those were ignored.
+    ExcessPixels { position: usize, nb_excess: usize },
+
+    /// Reserved bits of the packed field at `position` are set.
+    /// Only reported when decoding strictly.
+    ReservedBitsSet { position: usize },
+
+    /// The color table whose "Sort Flag" is in the packed field at `position`
+    /// is marked as sorted by decreasing importance, but its colors are not
+    /// sorted by decreasing number of uses in the image data.
+    /// Only reported when decoding strictly.
+    UnsortedColorTable { position: usize },
+
+    /// The frame whose Image Descriptor (or Plain Text Extension) is at
+    /// `position` goes past the edges of the logical screen.
+    /// Only reported when decoding strictly.
+    FrameOutsideScreen { position: usize },
+
+    /// The LZW minimum code size at `position` is out of the range of `2` to
+    /// `8` allowed by the GIF specification, though it could be decoded.
+    /// Only reported when decoding strictly.
+    LzwCodeSizeOutOfRange { code_size: u8, position: usize },
+
+    /// The NETSCAPE2.0 (or ANIMEXTS1.0) Application Extension at `position`
+    /// defines looping for a GIF having a single frame.
+    /// Only reported when decoding strictly.
+    LoopCountOnStillImage { position: usize },
+
+    /// An extension was found at `position` whereas the GIF declares to follow
+    /// the 87a version of the specification, which has none.
+    /// Only reported when decoding strictly.
+    ExtensionInGif87a { position: usize },
+}
+
+impl Warning {
+    /// Returns `true` if that warning is an error which was only recovered
+    /// from because decoding leniently: the GIF buffer is then damaged, and
+    /// would have failed to decode otherwise.
+    pub fn is_recovered_error(&self) -> bool {
+        match self {
+            Warning::UnexpectedEnd { .. }
+            | Warning::InvalidImageData { .. }
+            | Warning::UnknownExtension { .. }
+            | Warning::InvalidExtension { .. }
+            | Warning::SkippedBytes { .. } => true,
+            Warning::IncompleteFrame { .. }
+            | Warning::InvalidBackgroundColorIndex { .. }
+            | Warning::InvalidNetscapeSubBlock { .. }
+            | Warning::IgnoredApplicationExtension { .. }
+            | Warning::UnusedGraphicControlExtension { .. }
+            | Warning::ExcessPixels { .. }
+            | Warning::ReservedBitsSet { .. }
+            | Warning::UnsortedColorTable { .. }
+            | Warning::FrameOutsideScreen { .. }
+            | Warning::LzwCodeSizeOutOfRange { .. }
+            | Warning::LoopCountOnStillImage { .. }
+            | Warning::ExtensionInGif87a { .. } => false,
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Warning::UnexpectedEnd { position } => write!(
+                f,
+                "The GIF buffer ended unexpectedly, in the block at position {}.",
+                position
+            ),
+
+            Warning::IncompleteFrame {
+                position,
+                nb_decoded,
+                nb_pixels,
+            } => write!(
+                f,
+                "Only {} pixels out of {} were decoded for the frame at position {}.",
+                nb_decoded, nb_pixels, position
+            ),
+
+            Warning::InvalidImageData {
+                frame_position,
+                position,
+            } => write!(
+                f,
+                "Invalid image data at position {} for the frame at position {}.",
+                position, frame_position
+            ),
+
+            Warning::UnknownExtension { label, position } => write!(
+                f,
+                "Skipped an extension with the unknown label {} at position {}.",
+                label, position
+            ),
+
+            Warning::InvalidExtension { label, position } => write!(
+                f,
+                "Ignored an invalid extension with the label {} at position {}.",
+                label, position
+            ),
+
+            Warning::SkippedBytes { position, length } => write!(
+                f,
+                "Skipped {} unrecognized bytes at position {}.",
+                length, position
+            ),
+
+            Warning::InvalidBackgroundColorIndex { index, position } => write!(
+                f,
+                "The background color index {} at position {} is out of the \
+                    Global Color Table.",
+                index, position
+            ),
+
+            Warning::InvalidNetscapeSubBlock {
+                position,
+                id,
+                length,
+            } => write!(
+                f,
+                "Ignored a sub-block with the ID {} and a length of {} in the \
+                    NETSCAPE2.0 extension at position {}.",
+                id, length, position
+            ),
+
+            Warning::IgnoredApplicationExtension {
+                identifier,
+                position,
+            } => write!(
+                f,
+                "Ignored the unknown \"{}\" Application Extension at position {}.",
+                identifier, position
+            ),
+
+            Warning::UnusedGraphicControlExtension { position } => write!(
+                f,
+                "The Graphic Control Extension at position {} applies to no graphic.",
+                position
+            ),
+
+            Warning::ExcessPixels {
+                position,
+                nb_excess,
+            } => write!(
+                f,
+                "The image data of the frame at position {} contains {} pixels \
+                    too many.",
+                position, nb_excess
+            ),
+
+            Warning::ReservedBitsSet { position } => write!(
+                f,
+                "Reserved bits are set in the packed field at position {}.",
+                position
+            ),
+
+            Warning::UnsortedColorTable { position } => write!(
+                f,
+                "The color table whose Sort Flag is at position {} is marked as \
+                    sorted but is not.",
+                position
+            ),
+
+            Warning::FrameOutsideScreen { position } => write!(
+                f,
+                "The frame at position {} goes past the edges of the logical screen.",
+                position
+            ),
+
+            Warning::LzwCodeSizeOutOfRange {
+                code_size,
+                position,
+            } => write!(
+                f,
+                "The LZW minimum code size {} at position {} is out of the 2 to 8 \
+                    range.",
+                code_size, position
+            ),
+
+            Warning::LoopCountOnStillImage { position } => write!(
+                f,
+                "The looping extension at position {} applies to a single frame.",
+                position
+            ),
+
+            Warning::ExtensionInGif87a { position } => write!(
+                f,
+                "Found an extension at position {} in a GIF87a buffer.",
+                position
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gif_decoder::GifDecoder;
+    use crate::gif_reader::SliceReader;
+    use crate::options::DecodeOptions;
+    use crate::test_utils::GifBuilder;
+
+    /// Decode every frame of `gif` and returns the warnings encountered.
+    fn decode_warnings(gif: &[u8], lenient: bool, strict: bool) -> Vec<Warning> {
+        let options = DecodeOptions {
+            lenient,
+            strict,
+            ..Default::default()
+        };
+        let mut decoder = GifDecoder::with_options(SliceReader::new(gif), options).unwrap();
+        while decoder.next_frame().unwrap().is_some() {}
+        decoder.warnings().to_vec()
+    }
+
+    #[test]
+    fn reports_harmless_deviations_only_when_strict() {
+        let mut gif = GifBuilder::new(2, 1, 0)
+            .application_extension(b"NETSCAPE2.0", &[&[0x01, 0x00, 0x00]])
+            .graphic_control(0, None, 0)
+            .image(1, 0, 2, 1, &[1, 1])
+            .build();
+        // Global Color Table marked as sorted, the most used color not being
+        // the first one
+        gif[10] |= 0x08;
+        // Reserved bits of the Graphic Control Extension and Image Descriptor
+        gif[47] |= 0xE0;
+        gif[61] |= 0x18;
+        assert_eq!(decode_warnings(&gif, false, false), []);
+        assert_eq!(
+            decode_warnings(&gif, false, true),
+            [
+                Warning::ReservedBitsSet { position: 47 },
+                Warning::ReservedBitsSet { position: 61 },
+                Warning::FrameOutsideScreen { position: 52 },
+                Warning::UnsortedColorTable { position: 10 },
+                Warning::LoopCountOnStillImage { position: 25 },
+            ]
+        );
+
+        let mut gif = GifBuilder::new(2, 1, 0)
+            .graphic_control(0, None, 0)
+            .application_extension(b"UNKNOWN1.0\0", &[])
+            .image(0, 0, 2, 1, &[1, 1])
+            .build();
+        gif[..6].copy_from_slice(b"GIF87a");
+        assert_eq!(decode_warnings(&gif, false, false), []);
+        assert_eq!(
+            decode_warnings(&gif, false, true),
+            [
+                Warning::ExtensionInGif87a { position: 25 },
+                Warning::ExtensionInGif87a { position: 33 },
+                Warning::IgnoredApplicationExtension {
+                    identifier: "UNKNOWN1.0\0".to_owned(),
+                    position: 33
+                },
+            ]
+        );
+
+        // LZW minimum code size of 1, for the codes `Clear`, `1`, `0` then
+        // `End Of Information`
+        let mut gif = GifBuilder::new(2, 1, 0).build();
+        gif.pop();
+        gif.extend_from_slice(&[0x2C, 0, 0, 0, 0, 2, 0, 1, 0, 0x00]);
+        gif.extend_from_slice(&[0x01, 0x02, 0xC6, 0x00, 0x00, 0x3B]);
+        assert_eq!(decode_warnings(&gif, false, false), []);
+        assert_eq!(
+            decode_warnings(&gif, false, true),
+            [Warning::LzwCodeSizeOutOfRange {
+                code_size: 1,
+                position: 35
+            }]
+        );
+    }
+
+    #[test]
+    fn tells_recovered_errors_apart() {
+        // Truncated in the middle of the image data, after garbage
+        let gif = GifBuilder::new(2, 1, 0)
+            .image(0, 0, 2, 1, &[1, 1])
+            .image(0, 0, 2, 1, &[1, 1])
+            .build();
+        let mut damaged = gif[..25].to_vec();
+        damaged.extend_from_slice(&[0x42, 0x42]);
+        damaged.extend_from_slice(&gif[25..gif.len() - 4]);
+        let warnings = decode_warnings(&damaged, true, false);
+        assert!(matches!(
+            warnings[..],
+            [
+                Warning::SkippedBytes {
+                    position: 25,
+                    length: 2
+                },
+                Warning::UnexpectedEnd { .. },
+                ..
+            ]
+        ));
+        assert!(warnings[..2].iter().all(Warning::is_recovered_error));
+        // Not errors, even if only encountered in damaged GIFs
+        assert!(warnings[2..]
+            .iter()
+            .all(|w| matches!(w, Warning::IncompleteFrame { .. })));
+        assert!(!warnings[2..].iter().any(Warning::is_recovered_error));
+
+        let harmless = [
+            Warning::InvalidBackgroundColorIndex {
+                index: 4,
+                position: 11,
+            },
+            Warning::ReservedBitsSet { position: 10 },
+            Warning::UnusedGraphicControlExtension { position: 25 },
+            Warning::ExcessPixels {
+                position: 25,
+                nb_excess: 1,
+            },
+        ];
+        assert!(!harmless.iter().any(Warning::is_recovered_error));
+        let errors = [
+            Warning::InvalidImageData {
+                frame_position: 25,
+                position: 36,
+            },
+            Warning::UnknownExtension {
+                label: 0x42,
+                position: 25,
+            },
+            Warning::InvalidExtension {
+                label: 0xF9,
+                position: 25,
+            },
+        ];
+        assert!(errors.iter().all(Warning::is_recovered_error));
+    }
+}